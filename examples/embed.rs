@@ -0,0 +1,13 @@
+use gemini_rs::types::TaskType;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut route = gemini_rs::client().batch_embed_contents("text-embedding-004");
+    route.task_type(TaskType::RetrievalDocument);
+    route.texts(["The quick brown fox", "jumps over the lazy dog"]);
+
+    for embedding in route.await?.embeddings {
+        println!("{:?}", &embedding.values[..8]);
+    }
+    Ok(())
+}
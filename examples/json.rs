@@ -1,51 +1,32 @@
-use gemini_rs::types::{Schema, Type};
+use gemini_rs::GeminiSchema;
+use serde::Deserialize;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let person = Schema {
-        schema_type: Some(Type::Object),
-        properties: Some(
-            [
-                (
-                    "name".to_string(),
-                    Schema {
-                        schema_type: Some(Type::String),
-                        ..Default::default()
-                    },
-                ),
-                (
-                    "age".to_string(),
-                    Schema {
-                        schema_type: Some(Type::Integer),
-                        ..Default::default()
-                    },
-                ),
-                (
-                    "election_percentage".to_string(),
-                    Schema {
-                        schema_type: Some(Type::Number),
-                        ..Default::default()
-                    },
-                ),
-            ]
-            .into_iter()
-            .collect(),
-        ),
-        ..Default::default()
-    };
+/// A past or present US president.
+#[derive(Debug, Deserialize, GeminiSchema)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct President {
+    name: String,
+    /// Age when first taking office.
+    age: u32,
+    election_percentage: Option<f64>,
+    party: Party,
+}
 
-    let list_person = Schema {
-        schema_type: Some(Type::Array),
-        items: Some(Box::new(person)),
-        ..Default::default()
-    };
+#[derive(Debug, Deserialize, GeminiSchema)]
+enum Party {
+    Democratic,
+    Republican,
+    Other,
+}
 
-    let response = gemini_rs::chat("gemini-2.0-flash")
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let presidents = gemini_rs::chat("gemini-2.0-flash")
         .to_json()
-        .response_schema(list_person)
-        .json::<serde_json::Value>("List some US presidents")
+        .json_typed::<Vec<President>>("List some US presidents")
         .await?;
 
-    println!("{:#?}", response);
+    println!("{:#?}", presidents);
     Ok(())
 }
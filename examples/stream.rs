@@ -0,0 +1,14 @@
+use futures::StreamExt as _;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut route = gemini_rs::client().stream_generate_content("gemini-2.0-flash");
+    route.message("Write a long story about a robot learning to paint");
+
+    let mut stream = route.await?;
+    while let Some(response) = stream.next().await {
+        print!("{}", response?);
+    }
+    println!();
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use gemini_rs::{GeminiSchema, gemini_tool};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, GeminiSchema)]
+#[serde(rename_all = "lowercase")]
+enum Unit {
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(Serialize)]
+struct Weather {
+    temperature: f64,
+    conditions: &'static str,
+}
+
+/// Looks up the current weather in a city.
+#[gemini_tool]
+async fn get_weather(
+    /// The name of the city, such as "Paris".
+    city: String,
+    unit: Option<Unit>,
+) -> Result<Weather, String> {
+    if city.is_empty() {
+        return Err("no city given".into());
+    }
+    let temperature = match unit {
+        Some(Unit::Fahrenheit) => 64.4,
+        _ => 18.0,
+    };
+    Ok(Weather {
+        temperature,
+        conditions: "sunny",
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut chat = gemini_rs::chat("gemini-2.0-flash")
+        .register_tool(get_weather::declaration(), get_weather::call);
+
+    let response = chat
+        .send_message("What's the weather like in Paris, in fahrenheit?")
+        .await?;
+    println!("{response}");
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use gemini_rs::types::{Content, Role};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let client = gemini_rs::client();
+
+    let path = std::env::args().nth(1).ok_or("usage: upload <file.pdf>")?;
+    let file = tokio::fs::File::open(&path).await?;
+    let size = file.metadata().await?.len();
+
+    let mut upload = client.upload_reader(file, size, "application/pdf");
+    upload.display_name(&path);
+    upload.on_progress(|progress| eprintln!("{}/{} bytes", progress.uploaded, progress.total));
+    let file = upload.await?;
+    let file = client
+        .wait_for_file(&file.name, Duration::from_secs(2), Duration::from_secs(300))
+        .await?;
+
+    let mut chat = client.chat("gemini-2.0-flash");
+    chat.history_mut().push(Content {
+        role: Role::User,
+        parts: vec![file.to_part()],
+    });
+    println!("{}", chat.send_message("Summarize this document").await?);
+
+    client.delete_file(&file.name).await?;
+    Ok(())
+}
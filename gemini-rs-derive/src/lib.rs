@@ -0,0 +1,374 @@
+//! Derive macros for `gemini-rs`. Use them through the re-exports in that
+//! crate rather than depending on this one directly.
+
+mod tool;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    Attribute, Data, DeriveInput, Expr, ExprLit, Fields, Lit, LitStr, Meta, Type,
+    parse_macro_input, spanned::Spanned as _,
+};
+
+/// Implements `GeminiSchema` for a struct or a fieldless enum.
+///
+/// Structs become objects whose properties keep the field order; every field
+/// that is not an `Option` is required. Fieldless enums become strings limited
+/// to their variant names. Doc comments become descriptions, and serde's
+/// `rename`, `rename_all` and `skip` attributes are honoured.
+#[proc_macro_derive(GeminiSchema, attributes(serde))]
+pub fn derive_gemini_schema(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Turns an `async fn` into a tool a chat can call.
+///
+/// The function is kept as is, and a module of the same name is generated
+/// next to it with:
+///
+/// - `Args`, a struct with one field per argument, which deserializes the
+///   arguments of a function call;
+/// - `declaration()`, the `FunctionDeclaration` named after the function,
+///   described by its doc comments, and taking `Args`' schema as parameters;
+/// - `call(args: Args)`, which runs the function and serializes its output,
+///   or returns the error message if it returns an `Err`;
+/// - `dispatch(args: Value)`, which does the same for raw arguments.
+///
+/// `declaration()` and `call` are what `Chat::register_tool` expects. Argument
+/// types must implement `GeminiSchema` and `Deserialize`, and the output must
+/// be `Serialize`, or a `Result` of `Serialize` and `Display`.
+#[proc_macro_attribute]
+pub fn gemini_tool(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as syn::ItemFn);
+    if !args.is_empty() {
+        return syn::Error::new(
+            TokenStream::from(args).span(),
+            "#[gemini_tool] takes no arguments",
+        )
+        .into_compile_error()
+        .into();
+    }
+    tool::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let container = SerdeAttrs::parse(&input.attrs)?;
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => object(fields, &container)?,
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                quote!(<#ty as ::gemini_rs::GeminiSchema>::schema())
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "GeminiSchema supports structs with named fields and newtype structs",
+                ));
+            }
+        },
+        Data::Enum(data) => {
+            let mut values = Vec::new();
+            for variant in &data.variants {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(syn::Error::new(
+                        variant.span(),
+                        "GeminiSchema only supports enums without fields",
+                    ));
+                }
+                let attrs = SerdeAttrs::parse(&variant.attrs)?;
+                if attrs.skip {
+                    continue;
+                }
+                values.push(attrs.rename.unwrap_or_else(|| {
+                    container
+                        .rename_all
+                        .apply_to_variant(&variant.ident.to_string())
+                }));
+            }
+            quote! {
+                ::gemini_rs::types::Schema {
+                    schema_type: ::core::option::Option::Some(::gemini_rs::types::Type::String),
+                    format: ::core::option::Option::Some("enum".into()),
+                    enum_values: ::core::option::Option::Some(::std::vec![#(#values.into()),*]),
+                    ..::core::default::Default::default()
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "GeminiSchema does not support unions",
+            ));
+        }
+    };
+
+    let description = with_description(&input.attrs, body);
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(syn::parse_quote!(::gemini_rs::GeminiSchema));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::gemini_rs::GeminiSchema for #name #ty_generics #where_clause {
+            fn schema() -> ::gemini_rs::types::Schema {
+                #description
+            }
+        }
+    })
+}
+
+fn object(fields: &syn::FieldsNamed, container: &SerdeAttrs) -> syn::Result<TokenStream> {
+    let mut names = Vec::new();
+    let mut schemas = Vec::new();
+    let mut required = Vec::new();
+
+    for field in &fields.named {
+        let attrs = SerdeAttrs::parse(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field");
+        let ident = ident.to_string();
+        let name = attrs.rename.unwrap_or_else(|| {
+            container
+                .rename_all
+                .apply_to_field(ident.strip_prefix("r#").unwrap_or(&ident))
+        });
+
+        let ty = &field.ty;
+        schemas.push(with_description(
+            &field.attrs,
+            quote!(<#ty as ::gemini_rs::GeminiSchema>::schema()),
+        ));
+        if !attrs.default && !is_option(ty) {
+            required.push(name.clone());
+        }
+        names.push(name);
+    }
+
+    Ok(quote! {
+        ::gemini_rs::types::Schema {
+            schema_type: ::core::option::Option::Some(::gemini_rs::types::Type::Object),
+            properties: ::core::option::Option::Some(
+                [#((::std::string::String::from(#names), #schemas)),*].into_iter().collect(),
+            ),
+            required: ::core::option::Option::Some(::std::vec![#(#required.into()),*]),
+            property_ordering: ::core::option::Option::Some(::std::vec![#(#names.into()),*]),
+            ..::core::default::Default::default()
+        }
+    })
+}
+
+fn with_description(attrs: &[Attribute], schema: TokenStream) -> TokenStream {
+    let description = doc_string(attrs);
+    if description.is_empty() {
+        return schema;
+    }
+    quote! {
+        ::gemini_rs::types::Schema {
+            description: ::core::option::Option::Some(#description.into()),
+            ..#schema
+        }
+    }
+}
+
+/// Joins the doc comments in `attrs` into one string.
+fn doc_string(attrs: &[Attribute]) -> String {
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(doc), ..
+                }) => Some(doc.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    lines
+        .iter()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_owned()
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        Type::Group(group) => is_option(&group.elem),
+        Type::Paren(paren) => is_option(&paren.elem),
+        _ => false,
+    }
+}
+
+/// The subset of serde's attributes that changes the shape of the JSON.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: RenameRule,
+    skip: bool,
+    default: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                let path = &meta.path;
+                if path.is_ident("rename") {
+                    parsed.rename = Some(deserialize_name(&meta)?);
+                } else if path.is_ident("rename_all") {
+                    let rule = deserialize_name(&meta)?;
+                    parsed.rename_all = RenameRule::parse(&rule)
+                        .ok_or_else(|| meta.error(format!("unknown rename rule `{rule}`")))?;
+                } else if path.is_ident("skip") || path.is_ident("skip_deserializing") {
+                    parsed.skip = true;
+                } else if path.is_ident("default") {
+                    parsed.default = true;
+                    if meta.input.peek(syn::Token![=]) {
+                        meta.value()?.parse::<LitStr>()?;
+                    }
+                } else if meta.input.peek(syn::Token![=]) {
+                    meta.value()?.parse::<Expr>()?;
+                } else if meta.input.peek(syn::token::Paren) {
+                    meta.parse_nested_meta(|nested| {
+                        if nested.input.peek(syn::Token![=]) {
+                            nested.value()?.parse::<Expr>()?;
+                        }
+                        Ok(())
+                    })?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+/// Reads `= "name"` or `(deserialize = "name")`, ignoring a serialize-only
+/// name since responses are only ever deserialized.
+fn deserialize_name(meta: &syn::meta::ParseNestedMeta<'_>) -> syn::Result<String> {
+    if meta.input.peek(syn::Token![=]) {
+        return Ok(meta.value()?.parse::<LitStr>()?.value());
+    }
+
+    let mut name = None;
+    meta.parse_nested_meta(|nested| {
+        let value = nested.value()?.parse::<LitStr>()?.value();
+        if nested.path.is_ident("deserialize") {
+            name = Some(value);
+        }
+        Ok(())
+    })?;
+    name.ok_or_else(|| meta.error("expected a deserialize name"))
+}
+
+#[derive(Clone, Copy, Default)]
+enum RenameRule {
+    #[default]
+    None,
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &str) -> Option<Self> {
+        Some(match rule {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "PascalCase" => Self::Pascal,
+            "camelCase" => Self::Camel,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            _ => return None,
+        })
+    }
+
+    /// Renames a variant, written in PascalCase, exactly as serde does.
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            Self::None | Self::Pascal => variant.to_owned(),
+            Self::Lower => variant.to_ascii_lowercase(),
+            Self::Upper => variant.to_ascii_uppercase(),
+            Self::Camel => lowercase_first(variant),
+            Self::Snake => {
+                let mut snake = String::new();
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                snake
+            }
+            Self::ScreamingSnake => Self::Snake.apply_to_variant(variant).to_ascii_uppercase(),
+            Self::Kebab => Self::Snake.apply_to_variant(variant).replace('_', "-"),
+            Self::ScreamingKebab => Self::ScreamingSnake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+
+    /// Renames a field, written in snake_case, exactly as serde does.
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            Self::None | Self::Lower | Self::Snake => field.to_owned(),
+            Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+            Self::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            Self::Camel => lowercase_first(&Self::Pascal.apply_to_field(field)),
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+fn lowercase_first(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_ascii_lowercase().to_string() + chars.as_str()
+    })
+}
@@ -0,0 +1,132 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{FnArg, ItemFn, Pat, ReturnType, Type, spanned::Spanned as _};
+
+use crate::doc_string;
+
+pub(crate) fn expand(mut function: ItemFn) -> syn::Result<TokenStream> {
+    let signature = &function.sig;
+    if signature.asyncness.is_none() {
+        return Err(syn::Error::new(
+            signature.fn_token.span(),
+            "#[gemini_tool] functions must be async",
+        ));
+    }
+    if !signature.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            signature.generics.span(),
+            "#[gemini_tool] functions cannot be generic",
+        ));
+    }
+
+    let mut fields = Vec::new();
+    let mut idents = Vec::new();
+    for input in &mut function.sig.inputs {
+        let FnArg::Typed(input) = input else {
+            return Err(syn::Error::new(
+                input.span(),
+                "#[gemini_tool] functions cannot take self",
+            ));
+        };
+        let Pat::Ident(pat) = &*input.pat else {
+            return Err(syn::Error::new(
+                input.pat.span(),
+                "#[gemini_tool] arguments must be plain identifiers",
+            ));
+        };
+
+        // Docs and serde attributes describe the argument in `Args`; neither
+        // is allowed on a function parameter.
+        let attrs = std::mem::take(&mut input.attrs);
+        let ident = &pat.ident;
+        let ty = &input.ty;
+        fields.push(quote!(#(#attrs)* pub #ident: #ty));
+        idents.push(ident.clone());
+    }
+
+    let name = &function.sig.ident;
+    let vis = &function.vis;
+    let description = doc_string(&function.attrs);
+    let parameters = if idents.is_empty() {
+        quote!(::gemini_rs::__private::serde_json::Value::Null)
+    } else {
+        quote! {
+            ::gemini_rs::__private::serde_json::to_value(
+                <Args as ::gemini_rs::GeminiSchema>::schema(),
+            )
+            .expect("schemas always serialize")
+        }
+    };
+
+    let into_value = match &function.sig.output {
+        ReturnType::Type(_, ty) if is_result(ty) => quote! {
+            match output {
+                ::core::result::Result::Ok(output) => {
+                    ::gemini_rs::__private::serde_json::to_value(output)
+                        .map_err(|error| error.to_string())
+                }
+                ::core::result::Result::Err(error) => {
+                    ::core::result::Result::Err(error.to_string())
+                }
+            }
+        },
+        _ => quote! {
+            ::gemini_rs::__private::serde_json::to_value(output)
+                .map_err(|error| error.to_string())
+        },
+    };
+
+    Ok(quote! {
+        #function
+
+        #[allow(non_snake_case, dead_code)]
+        #vis mod #name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[derive(::gemini_rs::__private::serde::Deserialize, ::gemini_rs::GeminiSchema)]
+            #[serde(crate = "::gemini_rs::__private::serde")]
+            pub struct Args {
+                #(#fields),*
+            }
+
+            pub fn declaration() -> ::gemini_rs::types::FunctionDeclaration {
+                ::gemini_rs::types::FunctionDeclaration {
+                    name: ::std::string::String::from(::core::stringify!(#name)),
+                    description: ::std::string::String::from(#description),
+                    parameters: #parameters,
+                }
+            }
+
+            pub async fn call(
+                args: Args,
+            ) -> ::core::result::Result<::gemini_rs::__private::serde_json::Value, ::std::string::String>
+            {
+                let output = super::#name(#(args.#idents),*).await;
+                #into_value
+            }
+
+            pub async fn dispatch(
+                args: ::gemini_rs::__private::serde_json::Value,
+            ) -> ::core::result::Result<::gemini_rs::__private::serde_json::Value, ::std::string::String>
+            {
+                let args = ::gemini_rs::__private::serde_json::from_value(args)
+                    .map_err(|error| ::std::format!("invalid arguments: {error}"))?;
+                call(args).await
+            }
+        }
+    })
+}
+
+fn is_result(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Result"),
+        Type::Group(group) => is_result(&group.elem),
+        Type::Paren(paren) => is_result(&paren.elem),
+        _ => false,
+    }
+}
@@ -0,0 +1,123 @@
+#![allow(non_snake_case, non_camel_case_types)]
+
+use gemini_rs::GeminiSchema;
+use serde::Serialize;
+
+/// The property names of `value`'s schema, and the keys serde writes for it,
+/// both sorted.
+fn names<T: GeminiSchema + Serialize>(value: T) -> (Vec<String>, Vec<String>) {
+    let schema = T::schema();
+    let mut properties = schema.properties.unwrap().into_keys().collect::<Vec<_>>();
+    properties.sort();
+    let mut keys = serde_json::to_value(value)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.sort();
+    (properties, keys)
+}
+
+macro_rules! field_rules {
+    ($($name:ident: $rule:literal,)*) => {$(
+        #[test]
+        fn $name() {
+            #[derive(Default, Serialize, GeminiSchema)]
+            #[serde(rename_all = $rule)]
+            struct Fields {
+                first_name: String,
+                user_ID: u32,
+                _lead: bool,
+                x2_y: f64,
+                r#type: Option<String>,
+            }
+
+            let (properties, keys) = names(Fields::default());
+            assert_eq!(properties, keys);
+
+            let required = Fields::schema().required.unwrap();
+            assert_eq!(required.len(), 4);
+            assert!(required.iter().all(|name| keys.contains(name)));
+        }
+    )*};
+}
+
+field_rules! {
+    fields_unchanged: "snake_case",
+    fields_lowercase: "lowercase",
+    fields_uppercase: "UPPERCASE",
+    fields_pascal_case: "PascalCase",
+    fields_camel_case: "camelCase",
+    fields_screaming_snake_case: "SCREAMING_SNAKE_CASE",
+    fields_kebab_case: "kebab-case",
+    fields_screaming_kebab_case: "SCREAMING-KEBAB-CASE",
+}
+
+#[test]
+fn camel_case_only_capitalizes_after_underscores() {
+    #[derive(Default, Serialize, GeminiSchema)]
+    #[serde(rename_all = "camelCase")]
+    struct Fields {
+        user_ID: u32,
+        _lead: bool,
+    }
+
+    let (properties, _) = names(Fields::default());
+    assert_eq!(properties, ["lead", "userID"]);
+}
+
+macro_rules! variant_rules {
+    ($($name:ident: $rule:literal,)*) => {$(
+        #[test]
+        fn $name() {
+            #[derive(Serialize, GeminiSchema)]
+            #[serde(rename_all = $rule)]
+            enum Variants {
+                A,
+                FirstName,
+                HTTPError,
+                V2Beta,
+            }
+
+            let values = [
+                Variants::A,
+                Variants::FirstName,
+                Variants::HTTPError,
+                Variants::V2Beta,
+            ]
+            .map(|variant| serde_json::to_value(variant).unwrap().as_str().unwrap().to_owned());
+            assert_eq!(Variants::schema().enum_values.unwrap(), values);
+        }
+    )*};
+}
+
+variant_rules! {
+    variants_unchanged: "PascalCase",
+    variants_lowercase: "lowercase",
+    variants_uppercase: "UPPERCASE",
+    variants_camel_case: "camelCase",
+    variants_snake_case: "snake_case",
+    variants_screaming_snake_case: "SCREAMING_SNAKE_CASE",
+    variants_kebab_case: "kebab-case",
+    variants_screaming_kebab_case: "SCREAMING-KEBAB-CASE",
+}
+
+#[test]
+fn renamed_and_skipped_fields() {
+    #[derive(Default, Serialize, GeminiSchema)]
+    struct Fields {
+        #[serde(rename = "fullName")]
+        name: String,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        cache: u8,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    let schema = Fields::schema();
+    assert_eq!(schema.property_ordering.unwrap(), ["fullName", "tags"]);
+    assert_eq!(schema.required.unwrap(), ["fullName"]);
+}
@@ -0,0 +1,147 @@
+use gemini_rs::{GeminiSchema, gemini_tool};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+#[derive(Debug, Deserialize, GeminiSchema)]
+#[serde(rename_all = "lowercase")]
+enum Unit {
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(Serialize)]
+struct Weather {
+    city: String,
+    temperature: f64,
+    days_ahead: u8,
+}
+
+/// Looks up the weather in a city.
+///
+/// Temperatures are rounded.
+#[gemini_tool]
+async fn get_weather(
+    /// The name of the city, such as "Paris".
+    city: String,
+    #[serde(rename = "unitSystem")] unit: Option<Unit>,
+    days_ahead: u8,
+) -> Result<Weather, String> {
+    if city.is_empty() {
+        return Err("no city given".into());
+    }
+    let temperature = match unit {
+        Some(Unit::Fahrenheit) => 64.0,
+        _ => 18.0,
+    };
+    Ok(Weather {
+        city,
+        temperature,
+        days_ahead,
+    })
+}
+
+/// Says hello.
+#[gemini_tool]
+async fn greet() -> &'static str {
+    "hello"
+}
+
+/// Removes the `null`s schemas are serialized with, for readable assertions.
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(object) => object
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key, strip_nulls(value)))
+            .collect(),
+        Value::Array(items) => items.into_iter().map(strip_nulls).collect(),
+        value => value,
+    }
+}
+
+#[test]
+fn declaration() {
+    let declaration = serde_json::to_value(get_weather::declaration()).unwrap();
+    assert_eq!(
+        strip_nulls(declaration),
+        json!({
+            "name": "get_weather",
+            "description": "Looks up the weather in a city.\n\nTemperatures are rounded.",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "city": {
+                        "type": "string",
+                        "description": "The name of the city, such as \"Paris\".",
+                    },
+                    "unitSystem": {
+                        "type": "string",
+                        "format": "enum",
+                        "enum": ["celsius", "fahrenheit"],
+                        "nullable": true,
+                    },
+                    "days_ahead": {"type": "integer", "format": "int32"},
+                },
+                "required": ["city", "days_ahead"],
+                "propertyOrdering": ["city", "unitSystem", "days_ahead"],
+            },
+        })
+    );
+}
+
+#[test]
+fn declaration_without_arguments() {
+    let declaration = greet::declaration();
+    assert_eq!(declaration.name, "greet");
+    assert_eq!(declaration.description, "Says hello.");
+    assert_eq!(declaration.parameters, Value::Null);
+}
+
+#[tokio::test]
+async fn call() {
+    let output = get_weather::call(get_weather::Args {
+        city: "Paris".into(),
+        unit: Some(Unit::Fahrenheit),
+        days_ahead: 2,
+    })
+    .await;
+    assert_eq!(
+        output,
+        Ok(json!({"city": "Paris", "temperature": 64.0, "days_ahead": 2}))
+    );
+
+    assert_eq!(greet::call(greet::Args {}).await, Ok(json!("hello")));
+}
+
+#[tokio::test]
+async fn dispatch() {
+    let output =
+        get_weather::dispatch(json!({"city": "Oslo", "unitSystem": "celsius", "days_ahead": 0}))
+            .await;
+    assert_eq!(
+        output,
+        Ok(json!({"city": "Oslo", "temperature": 18.0, "days_ahead": 0}))
+    );
+
+    let error = get_weather::dispatch(json!({"city": "", "days_ahead": 1}))
+        .await
+        .unwrap_err();
+    assert_eq!(error, "no city given");
+}
+
+#[tokio::test]
+async fn dispatch_reports_bad_arguments() {
+    let error = get_weather::dispatch(json!({"city": "Oslo"}))
+        .await
+        .unwrap_err();
+    assert_eq!(error, "invalid arguments: missing field `days_ahead`");
+
+    let error =
+        get_weather::dispatch(json!({"city": "Oslo", "unitSystem": "kelvin", "days_ahead": 1}))
+            .await
+            .unwrap_err();
+    assert!(
+        error.starts_with("invalid arguments: unknown variant `kelvin`"),
+        "{error}"
+    );
+}
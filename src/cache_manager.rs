@@ -0,0 +1,285 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{Client, rate_limit, types};
+
+/// Moves large, repeated request prefixes into `cachedContents` automatically.
+///
+/// A prefix is the system instruction, the tools, the tool config and the
+/// leading contents of a request. Before a request is sent, the manager looks
+/// for the longest prefix it has already cached and, failing that, caches
+/// everything but the last content once it is estimated to exceed
+/// [`CacheManager::min_tokens`]. The request is then rewritten to reference
+/// the cache and to carry only the remaining contents.
+///
+/// Caches in use have their TTL extended; caches left unused for
+/// [`CacheManager::idle_timeout`] are deleted. Failing to create or extend a
+/// cache never fails the request, which is then simply sent uncached, and a
+/// prefix that could not be cached is not tried again for ten minutes.
+#[derive(Debug)]
+pub struct CacheManager {
+    min_tokens: u64,
+    ttl: Duration,
+    idle_timeout: Duration,
+    state: Mutex<State>,
+}
+
+/// How long a prefix that failed to be cached is left alone.
+const RETRY_FAILED_AFTER: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Default)]
+struct State {
+    caches: HashMap<u64, Entry>,
+    /// When caching each prefix last failed.
+    failed: HashMap<u64, Instant>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    prefix_len: usize,
+    expires_at: Instant,
+    last_used: Instant,
+}
+
+impl Default for CacheManager {
+    fn default() -> Self {
+        Self {
+            min_tokens: 4096,
+            ttl: Duration::from_secs(60 * 60),
+            idle_timeout: Duration::from_secs(10 * 60),
+            state: Mutex::default(),
+        }
+    }
+}
+
+impl CacheManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Estimated size a new prefix must reach before it is cached. Defaults to
+    /// 4096 tokens.
+    pub fn min_tokens(mut self, tokens: u64) -> Self {
+        self.min_tokens = tokens;
+        self
+    }
+
+    /// Lifetime given to caches on creation and whenever they are extended.
+    /// Defaults to one hour.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Defaults to ten minutes.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Deletes every cache this manager created.
+    pub async fn clear(&self, client: &Client) {
+        let caches = std::mem::take(&mut self.lock().caches);
+        for entry in caches.into_values() {
+            let _ = client.delete_cached_content(&entry.name).await;
+        }
+    }
+
+    /// Rewrites `body` to use a cached prefix, creating the cache if needed.
+    ///
+    /// The lock is only held to read and update the map, so requests never
+    /// wait on another request's cache calls.
+    pub(crate) async fn apply(
+        &self,
+        client: &Client,
+        model: &str,
+        body: &mut types::GenerateContent,
+    ) {
+        if body.cached_content.is_some() || body.contents.is_empty() {
+            return;
+        }
+
+        let hashes = prefix_hashes(model, body);
+        let now = Instant::now();
+        let (idle, known) = {
+            let mut state = self.lock();
+            let idle = state.sweep(now, self.idle_timeout);
+            let known = (0..hashes.len()).rev().find_map(|len| {
+                let entry = state.caches.get_mut(&hashes[len])?;
+                if entry.prefix_len != len {
+                    return None;
+                }
+                entry.last_used = now;
+                let stale = entry.expires_at.saturating_duration_since(now) <= self.ttl / 2;
+                Some((hashes[len], entry.name.clone(), len, stale))
+            });
+            (idle, known)
+        };
+
+        for name in idle {
+            let _ = client.delete_cached_content(&name).await;
+        }
+
+        let (name, prefix_len) = match known {
+            Some((hash, name, prefix_len, stale)) => {
+                if stale && !self.extend(client, hash, &name, now).await {
+                    return;
+                }
+                (name, prefix_len)
+            }
+            None => {
+                let prefix_len = body.contents.len() - 1;
+                let hash = hashes[prefix_len];
+                if self.lock().failed.contains_key(&hash) {
+                    return;
+                }
+                let Some(name) = self.create(client, model, body, hash, prefix_len).await else {
+                    return;
+                };
+                (name, prefix_len)
+            }
+        };
+
+        body.contents.drain(..prefix_len);
+        body.system_instruction = None;
+        body.tools.clear();
+        body.tool_config = None;
+        body.cached_content = Some(name);
+    }
+
+    /// Caches the first `prefix_len` contents of `body` and returns the name
+    /// of the cache, or `None` if the prefix is too small or caching failed.
+    async fn create(
+        &self,
+        client: &Client,
+        model: &str,
+        body: &types::GenerateContent,
+        hash: u64,
+        prefix_len: usize,
+    ) -> Option<String> {
+        let mut create = client.create_cached_content(model);
+        create.body.system_instruction = body.system_instruction.clone();
+        create.body.tools = body.tools.clone();
+        create.body.tool_config = body.tool_config.clone();
+        create.contents(body.contents[..prefix_len].to_vec());
+        create.ttl(self.ttl);
+
+        let size = serde_json::to_vec(&create.body).ok()?;
+        if rate_limit::estimate_tokens(&size) < self.min_tokens {
+            return None;
+        }
+
+        let cache = match create.await {
+            Ok(cache) => cache,
+            Err(_) => {
+                self.lock().failed.insert(hash, Instant::now());
+                return None;
+            }
+        };
+
+        let now = Instant::now();
+        let existing = {
+            let mut state = self.lock();
+            match state.caches.get_mut(&hash) {
+                // Another request cached the same prefix in the meantime.
+                Some(entry) if entry.prefix_len == prefix_len => {
+                    entry.last_used = now;
+                    Some(entry.name.clone())
+                }
+                _ => {
+                    state.caches.insert(
+                        hash,
+                        Entry {
+                            name: cache.name.clone(),
+                            prefix_len,
+                            expires_at: now + self.ttl,
+                            last_used: now,
+                        },
+                    );
+                    None
+                }
+            }
+        };
+
+        match existing {
+            Some(name) => {
+                let _ = client.delete_cached_content(&cache.name).await;
+                Some(name)
+            }
+            None => Some(cache.name),
+        }
+    }
+
+    /// Pushes the expiry of the cache back. Returns `false`, forgetting the
+    /// cache, if it is gone.
+    async fn extend(&self, client: &Client, hash: u64, name: &str, now: Instant) -> bool {
+        let mut update = client.update_cached_content(name);
+        update.ttl(self.ttl);
+        let extended = update.await.is_ok();
+
+        let mut state = self.lock();
+        if !extended {
+            state.caches.remove(&hash);
+        } else if let Some(entry) = state.caches.get_mut(&hash) {
+            entry.expires_at = now + self.ttl;
+        }
+        extended
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl State {
+    /// Forgets caches that have expired and failures that are old enough to
+    /// retry, and removes the caches that went idle, returning their names so
+    /// they can be deleted.
+    fn sweep(&mut self, now: Instant, idle_timeout: Duration) -> Vec<String> {
+        self.caches.retain(|_, entry| entry.expires_at > now);
+        self.failed
+            .retain(|_, failed_at| now.duration_since(*failed_at) < RETRY_FAILED_AFTER);
+
+        let idle = self
+            .caches
+            .iter()
+            .filter(|(_, entry)| now.duration_since(entry.last_used) >= idle_timeout)
+            .map(|(hash, _)| *hash)
+            .collect::<Vec<_>>();
+        idle.into_iter()
+            .filter_map(|hash| self.caches.remove(&hash))
+            .map(|entry| entry.name)
+            .collect()
+    }
+}
+
+/// Hashes of every prefix that leaves at least one content to send, indexed
+/// by the number of contents they cover.
+fn prefix_hashes(model: &str, body: &types::GenerateContent) -> Vec<u64> {
+    let mut hasher = DefaultHasher::new();
+    model.hash(&mut hasher);
+    write_json(&mut hasher, &body.system_instruction);
+    write_json(&mut hasher, &body.tools);
+    write_json(&mut hasher, &body.tool_config);
+
+    let mut hashes = Vec::with_capacity(body.contents.len());
+    for content in &body.contents {
+        hashes.push(hasher.clone().finish());
+        write_json(&mut hasher, content);
+    }
+    hashes
+}
+
+fn write_json(hasher: &mut DefaultHasher, value: &impl serde::Serialize) {
+    if let Ok(json) = serde_json::to_vec(value) {
+        hasher.write(&json);
+    }
+    hasher.write_u8(0xff);
+}
@@ -0,0 +1,217 @@
+use std::time::Duration;
+
+use reqwest::Method;
+
+use crate::{
+    client::{Endpoint, Formatter, Request},
+    types,
+};
+
+#[derive(Debug)]
+pub struct CreateCachedContent {
+    pub body: types::CachedContent,
+}
+
+impl CreateCachedContent {
+    pub fn new(model: &str) -> Self {
+        Self {
+            body: types::CachedContent {
+                model: format!("models/{}", model.strip_prefix("models/").unwrap_or(model)),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn display_name(&mut self, name: &str) {
+        self.body.display_name = Some(name.into());
+    }
+
+    pub fn system_instruction(&mut self, instruction: &str) {
+        self.body.system_instruction = Some(types::SystemInstructionContent {
+            parts: vec![types::SystemInstructionPart {
+                text: Some(instruction.into()),
+            }],
+        });
+    }
+
+    pub fn contents(&mut self, contents: Vec<types::Content>) {
+        self.body.contents = contents;
+    }
+
+    pub fn tools(&mut self, tools: Vec<types::Tools>) {
+        self.body.tools = tools;
+    }
+
+    pub fn ttl(&mut self, ttl: Duration) {
+        self.body.expire_time = None;
+        self.body.ttl = Some(types::format_duration(ttl));
+    }
+
+    /// An RFC 3339 timestamp, such as `2025-01-01T00:00:00Z`.
+    pub fn expire_time(&mut self, time: &str) {
+        self.body.ttl = None;
+        self.body.expire_time = Some(time.into());
+    }
+}
+
+impl Request for CreateCachedContent {
+    type Model = types::CachedContent;
+}
+
+impl Endpoint for CreateCachedContent {
+    type Body = types::CachedContent;
+
+    const METHOD: Method = Method::POST;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("cachedContents")
+    }
+
+    fn body(self) -> Option<Self::Body> {
+        Some(self.body)
+    }
+}
+
+#[derive(Debug)]
+pub struct GetCachedContent {
+    name: Box<str>,
+}
+
+impl GetCachedContent {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: cache_id(name).into(),
+        }
+    }
+}
+
+impl Request for GetCachedContent {
+    type Model = types::CachedContent;
+}
+
+impl Endpoint for GetCachedContent {
+    type Body = ();
+
+    const METHOD: Method = Method::GET;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("cachedContents/")?;
+        fmt.write_str(&self.name)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ListCachedContents {
+    page_size: Option<usize>,
+    page_token: Option<Box<str>>,
+}
+
+impl ListCachedContents {
+    pub fn page_size(&mut self, size: usize) {
+        self.page_size = size.into();
+    }
+
+    pub fn page_token(&mut self, token: &str) {
+        self.page_token = Some(Box::from(token));
+    }
+}
+
+impl Request for ListCachedContents {
+    type Model = types::CachedContents;
+}
+
+impl Endpoint for ListCachedContents {
+    type Body = ();
+
+    const METHOD: Method = Method::GET;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("cachedContents")?;
+        fmt.write_optional_query_param("pageSize", self.page_size.as_ref())?;
+        fmt.write_optional_query_param("pageToken", self.page_token.as_ref())
+    }
+}
+
+/// Changes when a cache expires; nothing else about a cache can be updated.
+#[derive(Debug)]
+pub struct UpdateCachedContent {
+    name: Box<str>,
+    body: types::CachedContent,
+}
+
+impl UpdateCachedContent {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: cache_id(name).into(),
+            body: types::CachedContent::default(),
+        }
+    }
+
+    pub fn ttl(&mut self, ttl: Duration) {
+        self.body.expire_time = None;
+        self.body.ttl = Some(types::format_duration(ttl));
+    }
+
+    /// An RFC 3339 timestamp, such as `2025-01-01T00:00:00Z`.
+    pub fn expire_time(&mut self, time: &str) {
+        self.body.ttl = None;
+        self.body.expire_time = Some(time.into());
+    }
+}
+
+impl Request for UpdateCachedContent {
+    type Model = types::CachedContent;
+}
+
+impl Endpoint for UpdateCachedContent {
+    type Body = types::CachedContent;
+
+    const METHOD: Method = Method::PATCH;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("cachedContents/")?;
+        fmt.write_str(&self.name)?;
+        let mask = if self.body.expire_time.is_some() {
+            "expireTime"
+        } else {
+            "ttl"
+        };
+        fmt.write_query_param("updateMask", &mask)
+    }
+
+    fn body(self) -> Option<Self::Body> {
+        Some(self.body)
+    }
+}
+
+#[derive(Debug)]
+pub struct DeleteCachedContent {
+    name: Box<str>,
+}
+
+impl DeleteCachedContent {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: cache_id(name).into(),
+        }
+    }
+}
+
+impl Request for DeleteCachedContent {
+    type Model = types::Empty;
+}
+
+impl Endpoint for DeleteCachedContent {
+    type Body = ();
+
+    const METHOD: Method = Method::DELETE;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("cachedContents/")?;
+        fmt.write_str(&self.name)
+    }
+}
+
+fn cache_id(name: &str) -> &str {
+    name.strip_prefix("cachedContents/").unwrap_or(name)
+}
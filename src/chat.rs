@@ -1,7 +1,15 @@
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{FutureExt as _, Stream, StreamExt as _, future::BoxFuture};
 
 use crate::{
-    Client, Result,
+    Client, Error, GeminiSchema, ResponseStream, Result,
+    client::GenerateContent,
     types::{self, Response},
 };
 
@@ -9,19 +17,42 @@ pub struct Chat<T> {
     model: Box<str>,
     client: Client,
     system_instruction: Option<Box<str>>,
+    cached_content: Option<Box<str>>,
     history: Vec<types::Content>,
     config: Option<types::GenerationConfig>,
+    last_response: Option<Response>,
+    tools: Vec<Tool>,
+    tool_config: Option<types::ToolConfig>,
+    max_tool_steps: usize,
+    repair_attempts: usize,
     phantom: PhantomData<T>,
 }
 
+type ToolHandler = Arc<
+    dyn Fn(serde_json::Value) -> BoxFuture<'static, std::result::Result<serde_json::Value, String>>
+        + Send
+        + Sync,
+>;
+
+struct Tool {
+    declaration: types::FunctionDeclaration,
+    handler: ToolHandler,
+}
+
 impl<T> Chat<T> {
     pub fn new(client: &Client, model: &str) -> Self {
         Self {
             model: model.into(),
             client: client.clone(),
             system_instruction: None,
+            cached_content: None,
             history: Vec::new(),
             config: None,
+            last_response: None,
+            tools: Vec::new(),
+            tool_config: None,
+            max_tool_steps: 10,
+            repair_attempts: 0,
             phantom: PhantomData,
         }
     }
@@ -36,8 +67,14 @@ impl<T> Chat<T> {
             model: self.model,
             client: self.client,
             system_instruction: self.system_instruction,
+            cached_content: self.cached_content,
             history: self.history,
             config: self.config,
+            last_response: self.last_response,
+            tools: self.tools,
+            tool_config: self.tool_config,
+            max_tool_steps: self.max_tool_steps,
+            repair_attempts: self.repair_attempts,
             phantom: PhantomData,
         }
     }
@@ -54,15 +91,83 @@ impl<T> Chat<T> {
         &mut self.history
     }
 
+    /// The response to the most recent successful turn, including usage and
+    /// safety data.
+    pub fn last_response(&self) -> Option<&Response> {
+        self.last_response.as_ref()
+    }
+
     pub fn system_instruction(mut self, instruction: &str) -> Self {
         self.system_instruction = Some(Box::from(instruction));
         self
     }
 
-    pub async fn generate_content(&mut self) -> Result<Response> {
-        let mut generate_content = self.client.generate_content(&self.model);
+    /// Starts from a cached prefix, so only the turns of this chat are sent.
+    ///
+    /// The cache carries its own system instruction, tools and tool config, so
+    /// any set on the chat are not sent while a cache is in use.
+    pub fn cached_content(mut self, name: &str) -> Self {
+        self.cached_content = Some(Box::from(name));
+        self
+    }
+
+    /// Controls how the model may call the registered tools.
+    pub fn tool_config(mut self, config: types::ToolConfig) -> Self {
+        self.tool_config = Some(config);
+        self
+    }
+
+    /// Declares a function the model may call, and the handler that runs it.
+    ///
+    /// Once a tool is registered, [`Chat::send_message`] answers function calls
+    /// itself: the handlers run, concurrently if the model makes several calls
+    /// at once, and their results are sent back until the model replies with
+    /// text. A handler error, or arguments that do not deserialize into `A`,
+    /// are reported to the model as `{"error": ...}` rather than failing the
+    /// turn. Results that are not JSON objects are sent as `{"result": ...}`.
+    pub fn register_tool<A, F, Fut, E>(
+        mut self,
+        declaration: types::FunctionDeclaration,
+        handler: F,
+    ) -> Self
+    where
+        A: serde::de::DeserializeOwned,
+        F: Fn(A) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<serde_json::Value, E>> + Send + 'static,
+        E: std::fmt::Display,
+    {
+        let handler = Arc::new(handler);
+        let handler: ToolHandler = Arc::new(move |args| {
+            let call = serde_json::from_value::<A>(args).map(|args| handler(args));
+            async move {
+                match call {
+                    Ok(future) => future.await.map_err(|error| error.to_string()),
+                    Err(error) => Err(format!("invalid arguments: {error}")),
+                }
+            }
+            .boxed()
+        });
+
+        self.tools
+            .retain(|tool| tool.declaration.name != declaration.name);
+        self.tools.push(Tool {
+            declaration,
+            handler,
+        });
+        self
+    }
+
+    /// How many rounds of function calls a single message may trigger before
+    /// [`Error::MaxToolSteps`] is returned. Defaults to 10.
+    pub fn max_tool_steps(mut self, steps: usize) -> Self {
+        self.max_tool_steps = steps;
+        self
+    }
 
-        if let Some(system_instruction) = &self.system_instruction {
+    fn prepare(&self, generate_content: &mut GenerateContent) {
+        if let Some(cached_content) = &self.cached_content {
+            generate_content.cached_content(cached_content);
+        } else if let Some(system_instruction) = &self.system_instruction {
             generate_content.system_instruction(system_instruction);
         }
 
@@ -70,17 +175,294 @@ impl<T> Chat<T> {
             generate_content.config(config.clone());
         }
 
+        if !self.tools.is_empty() && self.cached_content.is_none() {
+            generate_content.tools(vec![types::Tools {
+                function_declarations: self
+                    .tools
+                    .iter()
+                    .map(|tool| tool.declaration.clone())
+                    .collect(),
+            }]);
+        }
+
+        if let Some(tool_config) = &self.tool_config
+            && self.cached_content.is_none()
+        {
+            generate_content.tool_config(tool_config.clone());
+        }
+
         generate_content.contents(self.history.clone());
+    }
+
+    pub async fn generate_content(&mut self) -> Result<Response> {
+        let mut generate_content = self.client.generate_content(&self.model);
+        self.prepare(&mut generate_content);
+        generate_content.await
+    }
+
+    /// Counts the tokens of the request [`Chat::generate_content`] would send
+    /// right now.
+    pub async fn count_tokens(&self) -> Result<types::TokenCount> {
+        let mut generate_content = GenerateContent::new(self.model.clone());
+        self.prepare(&mut generate_content);
+
+        let mut count_tokens = self.client.count_tokens(&self.model);
+        count_tokens.generate_content(generate_content.body);
+        count_tokens.await
+    }
+
+    pub async fn stream_generate_content(&mut self) -> Result<ResponseStream> {
+        let mut generate_content = self.client.stream_generate_content(&self.model);
+        self.prepare(&mut generate_content);
         generate_content.await
     }
 
+    /// Sends a message and records the model's reply in the history.
+    ///
+    /// If the request fails, or the model does not reply because the prompt
+    /// was blocked or the candidate stopped empty, the user message is removed
+    /// again so the history never ends on an unanswered turn. With tools
+    /// registered, function calls are answered as described in
+    /// [`Chat::register_tool`], and the whole exchange is removed again if any
+    /// step fails.
     pub async fn send_message(&mut self, message: &str) -> Result<Response> {
+        let history_len = self.history.len();
         self.history.push(types::Content {
             role: types::Role::User,
             parts: vec![types::Part::text(message)],
         });
 
-        self.generate_content().await
+        match self.complete_turn().await {
+            Ok(response) => {
+                self.last_response = Some(response.clone());
+                Ok(response)
+            }
+            Err(error) => {
+                self.history.truncate(history_len);
+                Err(error)
+            }
+        }
+    }
+
+    async fn complete_turn(&mut self) -> Result<Response> {
+        let mut steps = 0;
+        loop {
+            let response = self.generate_content().await?;
+            self.history.push(reply(&response)?.clone());
+
+            let calls = response.function_calls().cloned().collect::<Vec<_>>();
+            if calls.is_empty() || self.tools.is_empty() {
+                return Ok(response);
+            }
+            if steps == self.max_tool_steps {
+                return Err(Error::MaxToolSteps(self.max_tool_steps));
+            }
+            steps += 1;
+
+            let parts =
+                futures::future::join_all(calls.iter().map(|call| self.call_tool(call))).await;
+            self.history.push(types::Content {
+                role: types::Role::User,
+                parts,
+            });
+        }
+    }
+
+    fn call_tool(&self, call: &types::FunctionCall) -> impl Future<Output = types::Part> + use<T> {
+        let handler = self
+            .tools
+            .iter()
+            .find(|tool| tool.declaration.name == call.name)
+            .map(|tool| Arc::clone(&tool.handler));
+        let call = call.clone();
+
+        async move {
+            let result = match handler {
+                Some(handler) => handler(call.args.clone()).await,
+                None => Err(format!("unknown function {}", call.name)),
+            };
+            let response = match result {
+                Ok(serde_json::Value::Object(object)) => serde_json::Value::Object(object),
+                Ok(value) => serde_json::json!({ "result": value }),
+                Err(error) => serde_json::json!({ "error": error }),
+            };
+            types::Part::function_response(types::FunctionResponse::new(&call, response))
+        }
+    }
+
+    /// Sends a message and yields the reply as text deltas.
+    ///
+    /// The user message and the assembled model reply are only kept in the
+    /// history once the stream has been read to the end; if it fails, ends
+    /// without a reply, or is dropped early, the history is restored to what
+    /// it was before the call.
+    pub async fn send_message_stream(&mut self, message: &str) -> Result<MessageStream<'_, T>> {
+        let history_len = self.history.len();
+        self.history.push(types::Content {
+            role: types::Role::User,
+            parts: vec![types::Part::text(message)],
+        });
+
+        match self.stream_generate_content().await {
+            Ok(inner) => Ok(MessageStream {
+                chat: self,
+                inner,
+                history_len,
+                parts: Vec::new(),
+                last: None,
+                state: StreamState::Pending,
+            }),
+            Err(error) => {
+                self.history.truncate(history_len);
+                Err(error)
+            }
+        }
+    }
+}
+
+/// The content of the first candidate, or the reason there is nothing to
+/// record as the model's turn.
+fn reply(response: &Response) -> Result<&types::Content> {
+    match response.candidates.first() {
+        Some(candidate) if !candidate.content.parts.is_empty() => Ok(&candidate.content),
+        _ => Err(no_reply(Some(response))),
+    }
+}
+
+/// Why `response`, the last one received, carries no reply.
+fn no_reply(response: Option<&Response>) -> Error {
+    if let Some(Err(error)) = response.map(Response::text) {
+        return error;
+    }
+    Error::CandidateFinished {
+        finish_reason: response
+            .and_then(|response| response.candidates.first())
+            .and_then(|candidate| candidate.finish_reason.clone())
+            .unwrap_or(types::FinishReason::FinishReasonUnspecified),
+    }
+}
+
+pub struct MessageStream<'chat, T> {
+    chat: &'chat mut Chat<T>,
+    inner: ResponseStream,
+    history_len: usize,
+    parts: Vec<types::Part>,
+    last: Option<Response>,
+    state: StreamState,
+}
+
+#[derive(PartialEq, Eq)]
+enum StreamState {
+    Pending,
+    Committed,
+    Failed,
+}
+
+impl<T> MessageStream<'_, T> {
+    fn merge(&mut self, response: Response) -> String {
+        let mut delta = String::new();
+        let Some(candidate) = response.candidates.first() else {
+            self.last = Some(response);
+            return delta;
+        };
+
+        for part in candidate.content.parts.clone() {
+            let types::PartData::Text(text) = &part.data else {
+                self.parts.push(part);
+                continue;
+            };
+            if !part.is_thought() {
+                delta.push_str(text);
+            }
+
+            match self.parts.last_mut() {
+                Some(last) if can_extend(last, &part) => {
+                    if let types::PartData::Text(last_text) = &mut last.data {
+                        last_text.push_str(text);
+                    }
+                    last.thought_signature = part.thought_signature;
+                }
+                _ => self.parts.push(part),
+            }
+        }
+
+        self.last = Some(response);
+        delta
+    }
+
+    /// Records the assembled reply, or rolls back if there is none, such as
+    /// when the prompt was blocked.
+    fn commit(&mut self) -> Result<()> {
+        if self.parts.is_empty() {
+            let error = no_reply(self.last.as_ref());
+            self.rollback();
+            return Err(error);
+        }
+
+        self.chat.history.push(types::Content {
+            role: types::Role::Model,
+            parts: std::mem::take(&mut self.parts),
+        });
+        self.chat.last_response = self.last.take();
+        self.state = StreamState::Committed;
+        Ok(())
+    }
+
+    fn rollback(&mut self) {
+        self.chat.history.truncate(self.history_len);
+        self.state = StreamState::Failed;
+    }
+}
+
+/// Whether the text of `next` can be appended to `last`, which is how text
+/// streamed over several chunks is put back together. A signature closes a
+/// part, and moves along when the part is extended.
+fn can_extend(last: &types::Part, next: &types::Part) -> bool {
+    matches!(last.data, types::PartData::Text(_))
+        && matches!(next.data, types::PartData::Text(_))
+        && last.is_thought() == next.is_thought()
+        && last.thought_signature.is_none()
+        && last.video_metadata.is_none()
+        && next.video_metadata.is_none()
+}
+
+impl<T> Stream for MessageStream<'_, T> {
+    type Item = Result<String>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.state != StreamState::Pending {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            return match this.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(response))) => {
+                    let delta = this.merge(response);
+                    if delta.is_empty() {
+                        continue;
+                    }
+                    Poll::Ready(Some(Ok(delta)))
+                }
+                Poll::Ready(Some(Err(error))) => {
+                    this.rollback();
+                    Poll::Ready(Some(Err(error)))
+                }
+                Poll::Ready(None) => match this.commit() {
+                    Ok(()) => Poll::Ready(None),
+                    Err(error) => Poll::Ready(Some(Err(error))),
+                },
+                Poll::Pending => Poll::Pending,
+            };
+        }
+    }
+}
+
+impl<T> Drop for MessageStream<'_, T> {
+    fn drop(&mut self) {
+        if self.state == StreamState::Pending {
+            self.rollback();
+        }
     }
 }
 
@@ -90,13 +472,144 @@ impl Chat<Json> {
         self
     }
 
+    /// How many times [`Chat::json`] asks the model to fix a reply that is
+    /// not valid JSON, does not match the response schema, or does not
+    /// deserialize. Each attempt lists the problems found and stays in the
+    /// history. Defaults to 0, returning the first error instead.
+    pub fn repair_attempts(mut self, attempts: usize) -> Self {
+        self.repair_attempts = attempts;
+        self
+    }
+
+    /// Sends a message and deserializes the reply.
+    ///
+    /// The reply is first checked against the response schema, if one is set,
+    /// so a mismatch is reported as [`Error::Schema`] with the path of every
+    /// problem rather than as an opaque deserialization error.
     pub async fn json<T: serde::de::DeserializeOwned>(&mut self, message: &str) -> Result<T> {
-        let response = self.send_message(message).await?;
-        let json = format!("{response}");
-        serde_json::from_str(&json).map_err(Into::into)
+        let mut response = self.send_message(message).await?;
+        let mut attempts = 0;
+        loop {
+            let error = match self.parse(&response) {
+                Ok(value) => return Ok(value),
+                Err(error @ (Error::Serde(_) | Error::Schema(_))) => error,
+                Err(error) => return Err(error),
+            };
+            if attempts == self.repair_attempts {
+                return Err(error);
+            }
+            attempts += 1;
+
+            let repair = format!(
+                "Your reply could not be used: {error}. \
+                 Reply again with only the corrected JSON."
+            );
+            response = self.send_message(&repair).await?;
+        }
+    }
+
+    fn parse<T: serde::de::DeserializeOwned>(&self, response: &Response) -> Result<T> {
+        let value = serde_json::from_str::<serde_json::Value>(&response.text()?)?;
+        if let Some(schema) = self
+            .config
+            .as_ref()
+            .and_then(|c| c.response_schema.as_ref())
+        {
+            let violations = schema.validate(&value);
+            if !violations.is_empty() {
+                return Err(Error::Schema(violations));
+            }
+        }
+        serde_json::from_value(value).map_err(Into::into)
+    }
+
+    /// Like [`Chat::json`], but first sets the response schema to `T`'s.
+    pub async fn json_typed<T>(&mut self, message: &str) -> Result<T>
+    where
+        T: GeminiSchema + serde::de::DeserializeOwned,
+    {
+        self.config_mut().response_schema = Some(T::schema());
+        self.json(message).await
     }
 }
 
 pub struct Text {}
 
 pub struct Json {}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt as _;
+
+    use super::{MessageStream, StreamState};
+    use crate::{Client, Error, types};
+
+    /// A stream over `events` for `chat`, as if "hello" had just been sent.
+    fn message_stream<'chat, T>(
+        chat: &'chat mut super::Chat<T>,
+        events: &[&str],
+    ) -> MessageStream<'chat, T> {
+        let history_len = chat.history.len();
+        chat.history.push(types::Content {
+            role: types::Role::User,
+            parts: vec![types::Part::text("hello")],
+        });
+        let responses: Vec<_> = events
+            .iter()
+            .map(|event| Ok(serde_json::from_str(event).unwrap()))
+            .collect();
+        MessageStream {
+            chat,
+            inner: futures::stream::iter(responses).boxed(),
+            history_len,
+            parts: Vec::new(),
+            last: None,
+            state: StreamState::Pending,
+        }
+    }
+
+    #[tokio::test]
+    async fn reply_is_committed() {
+        let mut chat = Client::new("key").chat("gemini-2.0-flash");
+        let stream = message_stream(
+            &mut chat,
+            &[
+                r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Hi"}]}}]}"#,
+                r#"{"candidates":[{"content":{"role":"model","parts":[{"text":" there"}]},"finishReason":"STOP"}]}"#,
+            ],
+        );
+
+        let deltas: Vec<_> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(deltas, ["Hi", " there"]);
+        assert_eq!(chat.history().len(), 2);
+        assert_eq!(chat.history()[1].parts.len(), 1);
+        assert!(chat.last_response().is_some());
+    }
+
+    #[tokio::test]
+    async fn stream_without_reply_rolls_back() {
+        let mut chat = Client::new("key").chat("gemini-2.0-flash");
+        let mut stream = message_stream(
+            &mut chat,
+            &[
+                r#"{"candidates":[{"content":{"role":"model","parts":[]},"finishReason":"SAFETY"}]}"#,
+            ],
+        );
+
+        let error = stream.next().await.unwrap().unwrap_err();
+        assert!(
+            matches!(
+                error,
+                Error::CandidateFinished {
+                    finish_reason: types::FinishReason::Safety
+                }
+            ),
+            "{error:?}"
+        );
+        assert!(stream.next().await.is_none());
+        drop(stream);
+
+        assert!(chat.history().is_empty());
+        assert!(chat.last_response().is_none());
+    }
+}
@@ -2,19 +2,38 @@ use std::{
     fmt::Write as _,
     ops::{Deref, DerefMut},
     sync::{Arc, LazyLock},
+    time::Duration,
 };
 
 use futures::FutureExt as _;
 use reqwest::Method;
 use secrecy::{ExposeSecret as _, SecretString};
 
-use crate::{Chat, Error, Result, chat, types};
+use crate::{
+    Chat, Error, Result,
+    cache_manager::CacheManager,
+    caches::{
+        CreateCachedContent, DeleteCachedContent, GetCachedContent, ListCachedContents,
+        UpdateCachedContent,
+    },
+    chat,
+    credentials::{self, Chain, CredentialProvider, StaticKey},
+    files::{DeleteFile, GetFile, ListFiles, UploadFile},
+    rate_limit::{self, RateLimiter, Reservation},
+    retry::{RetryEvent, RetryPolicy},
+    stream::{self, ResponseStream},
+    types,
+};
 
 const BASE_URI: &str = "https://generativelanguage.googleapis.com";
+const API_VERSION: &str = "v1beta";
+const API_KEY_HEADER: &str = "x-goog-api-key";
 
+#[derive(Debug)]
 pub struct Route<T> {
     client: Client,
     kind: T,
+    retry_policy: Option<RetryPolicy>,
 }
 
 impl<T> Route<T> {
@@ -22,6 +41,114 @@ impl<T> Route<T> {
         Self {
             client: client.clone(),
             kind,
+            retry_policy: None,
+        }
+    }
+
+    /// Overrides the client's retry policy for this request only.
+    pub fn retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = Some(policy);
+    }
+
+    pub(crate) fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Turns this route into one for a related request, keeping the client and
+    /// retry policy.
+    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> Route<U> {
+        Route {
+            client: self.client,
+            kind: f(self.kind),
+            retry_policy: self.retry_policy,
+        }
+    }
+}
+
+impl<T: Endpoint> Route<T> {
+    /// Sends the request, waiting for the rate limiter and retrying according
+    /// to the retry policy, and returns the response once it has a success
+    /// status.
+    pub(crate) async fn send(mut self) -> Result<(reqwest::Response, Reservation)> {
+        if let Some(manager) = &self.client.cache_manager
+            && let Some((model, body)) = self.kind.cacheable()
+        {
+            manager.apply(&self.client, &model, body).await;
+        }
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        self.kind.headers(&mut headers);
+        let mut request = self
+            .client
+            .reqwest
+            .request(T::METHOD, format!("{}/{self}", self.client.base_url))
+            .headers(headers);
+
+        let policy = self
+            .retry_policy
+            .clone()
+            .or_else(|| self.client.retry_policy.clone());
+        let model = self.kind.model().map(Box::<str>::from);
+
+        if let Some(body) = self.kind.body() {
+            request = request.json(&body);
+        }
+        let request = request.build()?;
+
+        let tokens = request
+            .body()
+            .and_then(reqwest::Body::as_bytes)
+            .map_or(0, rate_limit::estimate_tokens);
+
+        let mut attempt = 1;
+        loop {
+            let reservation = match (&self.client.rate_limiter, &model) {
+                (Some(limiter), Some(model)) => limiter.acquire(model, tokens).await,
+                _ => Reservation::none(),
+            };
+
+            let request = request
+                .try_clone()
+                .expect("request bodies are always buffered");
+            let (error, http_code) = match self.client.execute(request).await {
+                Ok(response) => return Ok((response, reservation)),
+                Err(failure) => failure,
+            };
+
+            let Some(policy) = &policy else {
+                return Err(error);
+            };
+            let Some(delay) = policy.next_delay(attempt, &error, http_code) else {
+                return Err(error);
+            };
+
+            policy.notify(&RetryEvent {
+                attempt,
+                delay,
+                error: &error,
+            });
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+impl<T: Endpoint> Route<T> {
+    pub(crate) async fn fetch<M: serde::de::DeserializeOwned>(self) -> Result<(M, Reservation)> {
+        let (response, reservation) = self.send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+        match types::ApiResponse::<M>::parse(&body) {
+            Ok(types::ApiResponse::Ok(response)) => Ok((response, reservation)),
+            Ok(types::ApiResponse::Err(api_error)) => Err(Error::Gemini {
+                status: status.as_u16(),
+                detail: api_error.error,
+            }),
+            Err(source) => Err(Error::Decode {
+                status: status.as_u16(),
+                body: truncate(&String::from_utf8_lossy(&body), MAX_ERROR_BODY),
+                source,
+            }),
         }
     }
 }
@@ -32,20 +159,63 @@ impl<T: Request> IntoFuture for Route<T> {
 
     fn into_future(self) -> Self::IntoFuture {
         async move {
-            let mut request = self
-                .client
-                .reqwest
-                .request(T::METHOD, format!("{BASE_URI}/{self}"));
-
-            if let Some(body) = self.kind.body() {
-                request = request.json(&body);
+            let (response, reservation) = self.fetch::<T::Model>().await?;
+            if let Some(tokens) = T::prompt_tokens(&response) {
+                reservation.reconcile(tokens);
             }
+            Ok(response)
+        }
+        .boxed()
+    }
+}
+
+impl IntoFuture for Route<StreamGenerateContent> {
+    type Output = Result<ResponseStream>;
+    type IntoFuture = futures::future::BoxFuture<'static, Self::Output>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        async move {
+            let (response, _) = self.send().await?;
+            Ok(stream::response_stream(response))
+        }
+        .boxed()
+    }
+}
 
-            let response = request.send().await?;
-            match response.json::<types::ApiResponse<T::Model>>().await? {
-                types::ApiResponse::Ok(response) => Ok(response),
-                types::ApiResponse::Err(api_error) => Err(Error::Gemini(api_error.error)),
+impl IntoFuture for Route<BatchEmbedContents> {
+    type Output = Result<types::BatchEmbedContentsResponse>;
+    type IntoFuture = futures::future::BoxFuture<'static, Self::Output>;
+
+    /// Sends the requests in batches of at most `BATCH_EMBED_LIMIT` and
+    /// returns the embeddings in input order.
+    fn into_future(self) -> Self::IntoFuture {
+        async move {
+            let Route {
+                client,
+                kind,
+                retry_policy,
+            } = self;
+            let model = kind.model.clone();
+            let mut requests = kind.into_requests().into_iter().peekable();
+            let mut embeddings = Vec::with_capacity(requests.len());
+
+            while requests.peek().is_some() {
+                let mut route = Route::new(
+                    &client,
+                    BatchEmbedContents {
+                        model: model.clone(),
+                        requests: requests.by_ref().take(BATCH_EMBED_LIMIT).collect(),
+                        task_type: None,
+                        output_dimensionality: None,
+                    },
+                );
+                route.retry_policy = retry_policy.clone();
+
+                let (response, _) = route.fetch::<types::BatchEmbedContentsResponse>().await?;
+                embeddings.extend(response.embeddings);
             }
+
+            Ok(types::BatchEmbedContentsResponse { embeddings })
         }
         .boxed()
     }
@@ -65,15 +235,19 @@ impl<T> DerefMut for Route<T> {
     }
 }
 
-impl<T: Request> std::fmt::Display for Route<T> {
+impl<T: Endpoint> std::fmt::Display for Route<T> {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut fmt = Formatter::new(fmt);
-        self.kind.format_uri(&mut fmt)?;
-        fmt.write_query_param("key", &self.client.key.expose_secret())
+        if T::UPLOAD {
+            fmt.write_str("upload/")?;
+        }
+        fmt.write_str(&self.client.api_version)?;
+        fmt.write_char('/')?;
+        self.kind.format_uri(&mut fmt)
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Client {
     inner: Arc<ClientInner>,
 }
@@ -86,19 +260,43 @@ impl Deref for Client {
     }
 }
 
+/// Looks the key up in `GEMINI_API_KEY` or `GEMINI_API_KEY_FILE` on every
+/// request, so a missing key surfaces as [`Error::Credentials`] rather than a
+/// panic.
 impl Default for Client {
     fn default() -> Self {
-        Self {
-            inner: ClientInner::new(None),
-        }
+        Self::builder()
+            .build()
+            .expect("failed to initialize HTTP client")
     }
 }
 
 impl Client {
     pub fn new(key: impl Into<SecretString>) -> Self {
-        Self {
-            inner: ClientInner::new(Some(key.into())),
+        Self::try_new(key).expect("failed to initialize HTTP client")
+    }
+
+    pub fn try_new(key: impl Into<SecretString>) -> Result<Self> {
+        Self::builder().api_key(key).build()
+    }
+
+    /// Like [`Client::default`], but fails up front when neither
+    /// `GEMINI_API_KEY` nor `GEMINI_API_KEY_FILE` is set.
+    pub fn try_from_env() -> Result<Self> {
+        if std::env::var_os(credentials::API_KEY_VAR).is_none()
+            && std::env::var_os(credentials::API_KEY_FILE_VAR).is_none()
+        {
+            return Err(Error::Credentials(format!(
+                "neither {} nor {} is set",
+                credentials::API_KEY_VAR,
+                credentials::API_KEY_FILE_VAR,
+            )));
         }
+        Self::builder().build()
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
     }
 
     pub fn chat(&self, model: &str) -> Chat<chat::Text> {
@@ -113,12 +311,123 @@ impl Client {
         Route::new(self, GenerateContent::new(model.into()))
     }
 
+    pub fn count_tokens(&self, model: &str) -> Route<CountTokens> {
+        Route::new(self, CountTokens::new(model.into()))
+    }
+
+    pub fn embed_content(&self, model: &str) -> Route<EmbedContent> {
+        Route::new(self, EmbedContent::new(model.into()))
+    }
+
+    pub fn batch_embed_contents(&self, model: &str) -> Route<BatchEmbedContents> {
+        Route::new(self, BatchEmbedContents::new(model.into()))
+    }
+
+    /// Uploads `data` through the resumable upload protocol. The returned file
+    /// may still be processing; see [`Client::wait_for_file`].
+    pub fn upload_file(&self, data: Vec<u8>, mime_type: &str) -> Route<UploadFile> {
+        Route::new(self, UploadFile::new(data, mime_type))
+    }
+
+    /// Like [`Client::upload_file`], but streams `size` bytes from `reader` in
+    /// chunks instead of holding the whole file in memory.
+    pub fn upload_reader(
+        &self,
+        reader: impl tokio::io::AsyncRead + Send + Unpin + 'static,
+        size: u64,
+        mime_type: &str,
+    ) -> Route<UploadFile> {
+        Route::new(self, UploadFile::from_reader(reader, size, mime_type))
+    }
+
+    /// `name` may be given with or without the `files/` prefix.
+    pub fn file(&self, name: &str) -> Route<GetFile> {
+        Route::new(self, GetFile::new(name))
+    }
+
+    pub fn files(&self) -> Route<ListFiles> {
+        Route::new(self, ListFiles::default())
+    }
+
+    pub fn delete_file(&self, name: &str) -> Route<DeleteFile> {
+        Route::new(self, DeleteFile::new(name))
+    }
+
+    /// Polls the file every `interval` until it has been processed, for at
+    /// most `timeout`.
+    ///
+    /// Returns the file once it is [`types::FileState::Active`], and
+    /// [`Error::FileFailed`] if processing failed. Any other state, including
+    /// one this crate does not know, counts as still processing until
+    /// `timeout` runs out, which fails with [`Error::FileNotReady`].
+    pub async fn wait_for_file(
+        &self,
+        name: &str,
+        interval: Duration,
+        timeout: Duration,
+    ) -> Result<types::File> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let file = self.file(name).await?;
+            match file.state {
+                types::FileState::Active => return Ok(file),
+                types::FileState::Failed => {
+                    let message = file
+                        .error
+                        .map(|error| error.message)
+                        .unwrap_or_else(|| "file processing failed".into());
+                    return Err(Error::FileFailed {
+                        name: file.name,
+                        message,
+                    });
+                }
+                _ => {}
+            }
+
+            let left = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if left.is_zero() {
+                return Err(Error::FileNotReady {
+                    name: file.name,
+                    state: file.state,
+                    timeout,
+                });
+            }
+            tokio::time::sleep(interval.min(left)).await;
+        }
+    }
+
+    pub fn create_cached_content(&self, model: &str) -> Route<CreateCachedContent> {
+        Route::new(self, CreateCachedContent::new(model))
+    }
+
+    /// `name` may be given with or without the `cachedContents/` prefix.
+    pub fn cached_content(&self, name: &str) -> Route<GetCachedContent> {
+        Route::new(self, GetCachedContent::new(name))
+    }
+
+    pub fn cached_contents(&self) -> Route<ListCachedContents> {
+        Route::new(self, ListCachedContents::default())
+    }
+
+    pub fn update_cached_content(&self, name: &str) -> Route<UpdateCachedContent> {
+        Route::new(self, UpdateCachedContent::new(name))
+    }
+
+    pub fn delete_cached_content(&self, name: &str) -> Route<DeleteCachedContent> {
+        Route::new(self, DeleteCachedContent::new(name))
+    }
+
+    pub fn stream_generate_content(&self, model: &str) -> Route<StreamGenerateContent> {
+        Route::new(self, StreamGenerateContent::new(model.into()))
+    }
+
     pub fn instance() -> Client {
         static STATIC_INSTANCE: LazyLock<Client> = LazyLock::new(Client::default);
         STATIC_INSTANCE.clone()
     }
 }
 
+#[derive(Debug)]
 pub struct GenerateContent {
     model: Box<str>,
     pub body: types::GenerateContent,
@@ -148,6 +457,20 @@ impl GenerateContent {
         self.body.contents = contents;
     }
 
+    pub fn tools(&mut self, tools: Vec<types::Tools>) {
+        self.body.tools = tools;
+    }
+
+    pub fn tool_config(&mut self, config: types::ToolConfig) {
+        self.body.tool_config = Some(config);
+    }
+
+    /// Continues from a cached prefix; the cache already holds the system
+    /// instruction, tools and tool config, so they must not be set again.
+    pub fn cached_content(&mut self, name: &str) {
+        self.body.cached_content = Some(name.into());
+    }
+
     pub fn message(&mut self, message: &str) {
         self.body.contents.push(types::Content {
             role: types::Role::User,
@@ -158,23 +481,295 @@ impl GenerateContent {
 
 impl Request for GenerateContent {
     type Model = types::Response;
+
+    fn prompt_tokens(model: &Self::Model) -> Option<u64> {
+        model
+            .usage_metadata
+            .as_ref()
+            .map(|usage| usage.prompt_token_count)
+    }
+}
+
+impl Endpoint for GenerateContent {
     type Body = types::GenerateContent;
 
     const METHOD: Method = Method::POST;
 
     fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
-        fmt.write_str("v1beta/")?;
         fmt.write_str("models/")?;
         fmt.write_str(&self.model)?;
         fmt.write_str(":generateContent")
     }
 
+    fn model(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn cacheable(&mut self) -> Option<(Box<str>, &mut types::GenerateContent)> {
+        Some((self.model.clone(), &mut self.body))
+    }
+
     fn body(self) -> Option<Self::Body> {
         Some(self.body)
     }
 }
 
-#[derive(Default)]
+/// Same request as [`GenerateContent`], answered incrementally over
+/// server-sent events. Awaiting the route yields a [`ResponseStream`].
+#[derive(Debug)]
+pub struct StreamGenerateContent {
+    inner: GenerateContent,
+}
+
+impl StreamGenerateContent {
+    pub fn new(model: Box<str>) -> Self {
+        Self {
+            inner: GenerateContent::new(model),
+        }
+    }
+}
+
+impl Deref for StreamGenerateContent {
+    type Target = GenerateContent;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for StreamGenerateContent {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl Endpoint for StreamGenerateContent {
+    type Body = types::GenerateContent;
+
+    const METHOD: Method = Method::POST;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("models/")?;
+        fmt.write_str(&self.model)?;
+        fmt.write_str(":streamGenerateContent")?;
+        fmt.write_query_param("alt", &"sse")
+    }
+
+    fn model(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn cacheable(&mut self) -> Option<(Box<str>, &mut types::GenerateContent)> {
+        self.inner.cacheable()
+    }
+
+    fn body(self) -> Option<Self::Body> {
+        Some(self.inner.body)
+    }
+}
+
+#[derive(Debug)]
+pub struct CountTokens {
+    model: Box<str>,
+    pub body: types::CountTokens,
+}
+
+impl CountTokens {
+    pub fn new(model: Box<str>) -> Self {
+        Self {
+            model,
+            body: types::CountTokens::default(),
+        }
+    }
+
+    pub fn contents(&mut self, contents: Vec<types::Content>) {
+        self.body.generate_content_request = None;
+        self.body.contents = contents;
+    }
+
+    /// Counts a whole request, including its system instruction, tools and
+    /// config, instead of only its contents.
+    pub fn generate_content(&mut self, body: types::GenerateContent) {
+        self.body.contents.clear();
+        self.body.generate_content_request = Some(types::GenerateContentRequest {
+            model: format!("models/{}", self.model),
+            body,
+        });
+    }
+}
+
+impl Request for CountTokens {
+    type Model = types::TokenCount;
+}
+
+impl Endpoint for CountTokens {
+    type Body = types::CountTokens;
+
+    const METHOD: Method = Method::POST;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("models/")?;
+        fmt.write_str(&self.model)?;
+        fmt.write_str(":countTokens")
+    }
+
+    fn body(self) -> Option<Self::Body> {
+        Some(self.body)
+    }
+}
+
+#[derive(Debug)]
+pub struct EmbedContent {
+    model: Box<str>,
+    pub body: types::EmbedContentRequest,
+}
+
+impl EmbedContent {
+    pub fn new(model: Box<str>) -> Self {
+        Self {
+            model,
+            body: types::EmbedContentRequest::default(),
+        }
+    }
+
+    pub fn text(&mut self, text: &str) {
+        self.body.content.parts = vec![types::Part::text(text)];
+    }
+
+    pub fn content(&mut self, content: types::Content) {
+        self.body.content = content;
+    }
+
+    pub fn task_type(&mut self, task_type: types::TaskType) {
+        self.body.task_type = Some(task_type);
+    }
+
+    /// Only used with [`types::TaskType::RetrievalDocument`].
+    pub fn title(&mut self, title: &str) {
+        self.body.title = Some(title.into());
+    }
+
+    pub fn output_dimensionality(&mut self, dimensions: u32) {
+        self.body.output_dimensionality = Some(dimensions);
+    }
+}
+
+impl Request for EmbedContent {
+    type Model = types::EmbedContentResponse;
+}
+
+impl Endpoint for EmbedContent {
+    type Body = types::EmbedContentRequest;
+
+    const METHOD: Method = Method::POST;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("models/")?;
+        fmt.write_str(&self.model)?;
+        fmt.write_str(":embedContent")
+    }
+
+    fn model(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn body(self) -> Option<Self::Body> {
+        Some(self.body)
+    }
+}
+
+/// Maximum number of requests the API accepts in one `batchEmbedContents`
+/// call.
+const BATCH_EMBED_LIMIT: usize = 100;
+
+/// Embeds any number of contents; larger inputs are split into several calls
+/// transparently.
+#[derive(Debug)]
+pub struct BatchEmbedContents {
+    model: Box<str>,
+    requests: Vec<types::EmbedContentRequest>,
+    task_type: Option<types::TaskType>,
+    output_dimensionality: Option<u32>,
+}
+
+impl BatchEmbedContents {
+    pub fn new(model: Box<str>) -> Self {
+        Self {
+            model,
+            requests: Vec::new(),
+            task_type: None,
+            output_dimensionality: None,
+        }
+    }
+
+    pub fn text(&mut self, text: &str) {
+        self.request(types::EmbedContentRequest {
+            content: types::Content {
+                role: types::Role::User,
+                parts: vec![types::Part::text(text)],
+            },
+            ..Default::default()
+        });
+    }
+
+    pub fn texts<'a>(&mut self, texts: impl IntoIterator<Item = &'a str>) {
+        for text in texts {
+            self.text(text);
+        }
+    }
+
+    pub fn request(&mut self, request: types::EmbedContentRequest) {
+        self.requests.push(request);
+    }
+
+    /// Applied to every request that does not set its own task type.
+    pub fn task_type(&mut self, task_type: types::TaskType) {
+        self.task_type = Some(task_type);
+    }
+
+    /// Applied to every request that does not set its own dimensionality.
+    pub fn output_dimensionality(&mut self, dimensions: u32) {
+        self.output_dimensionality = Some(dimensions);
+    }
+
+    fn into_requests(self) -> Vec<types::EmbedContentRequest> {
+        let model = format!("models/{}", self.model);
+        self.requests
+            .into_iter()
+            .map(|mut request| {
+                request.model = Some(model.clone());
+                request.task_type = request.task_type.or_else(|| self.task_type.clone());
+                request.output_dimensionality =
+                    request.output_dimensionality.or(self.output_dimensionality);
+                request
+            })
+            .collect()
+    }
+}
+
+impl Endpoint for BatchEmbedContents {
+    type Body = types::BatchEmbedContents;
+
+    const METHOD: Method = Method::POST;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("models/")?;
+        fmt.write_str(&self.model)?;
+        fmt.write_str(":batchEmbedContents")
+    }
+
+    fn model(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn body(self) -> Option<Self::Body> {
+        Some(types::BatchEmbedContents {
+            requests: self.into_requests(),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct Models {
     page_size: Option<usize>,
     page_token: Option<Box<str>>,
@@ -192,12 +787,14 @@ impl Models {
 
 impl Request for Models {
     type Model = types::Models;
+}
+
+impl Endpoint for Models {
     type Body = ();
 
     const METHOD: Method = Method::GET;
 
     fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
-        fmt.write_str("v1beta/")?;
         fmt.write_str("models")?;
         fmt.write_optional_query_param("page_size", self.page_size.as_ref())?;
         fmt.write_optional_query_param("page_token", self.page_token.as_ref())
@@ -231,7 +828,11 @@ impl<'me, 'buffer> Formatter<'me, 'buffer> {
         }
     }
 
-    fn write_query_param(&mut self, key: &str, value: &impl std::fmt::Display) -> std::fmt::Result {
+    pub(crate) fn write_query_param(
+        &mut self,
+        key: &str,
+        value: &impl std::fmt::Display,
+    ) -> std::fmt::Result {
         if self.is_first {
             self.formatter.write_char('?')?;
             self.is_first = false;
@@ -244,7 +845,7 @@ impl<'me, 'buffer> Formatter<'me, 'buffer> {
         std::fmt::Display::fmt(value, self.formatter)
     }
 
-    fn write_optional_query_param(
+    pub(crate) fn write_optional_query_param(
         &mut self,
         key: &str,
         value: Option<&impl std::fmt::Display>,
@@ -257,31 +858,280 @@ impl<'me, 'buffer> Formatter<'me, 'buffer> {
     }
 }
 
+/// Configures a [`Client`] before it is created.
+///
+/// Every option has the same default as [`Client::new`], so only the settings
+/// that differ need to be given.
+#[derive(Default)]
+pub struct ClientBuilder {
+    credentials: Option<Box<dyn CredentialProvider>>,
+    base_url: Option<Box<str>>,
+    api_version: Option<Box<str>>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    proxy: Option<reqwest::Proxy>,
+    user_agent: Option<Box<str>>,
+    default_headers: reqwest::header::HeaderMap,
+    reqwest: Option<reqwest::Client>,
+    retry_policy: Option<RetryPolicy>,
+    rate_limiter: Option<RateLimiter>,
+    cache_manager: Option<CacheManager>,
+}
+
+impl ClientBuilder {
+    /// Shorthand for [`ClientBuilder::credentials`] with a [`StaticKey`].
+    pub fn api_key(self, key: impl Into<SecretString>) -> Self {
+        self.credentials(StaticKey::new(key))
+    }
+
+    /// Defaults to [`Chain::from_env`].
+    pub fn credentials(mut self, provider: impl CredentialProvider) -> Self {
+        self.credentials = Some(Box::new(provider));
+        self
+    }
+
+    /// Defaults to `https://generativelanguage.googleapis.com`.
+    pub fn base_url(mut self, url: &str) -> Self {
+        self.base_url = Some(url.trim_end_matches('/').into());
+        self
+    }
+
+    /// Defaults to `v1beta`.
+    pub fn api_version(mut self, version: &str) -> Self {
+        self.api_version = Some(version.trim_matches('/').into());
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Total time allowed for a request, from connecting until the body has
+    /// been read.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn default_header(
+        mut self,
+        name: reqwest::header::HeaderName,
+        value: reqwest::header::HeaderValue,
+    ) -> Self {
+        self.default_headers.insert(name, value);
+        self
+    }
+
+    pub fn default_headers(mut self, headers: reqwest::header::HeaderMap) -> Self {
+        self.default_headers.extend(headers);
+        self
+    }
+
+    /// Uses an already configured `reqwest::Client`.
+    ///
+    /// The timeouts, proxy, user agent and default headers of this builder are
+    /// ignored in that case, as they can only be applied when the HTTP client
+    /// is built.
+    pub fn reqwest_client(mut self, client: reqwest::Client) -> Self {
+        self.reqwest = Some(client);
+        self
+    }
+
+    /// Requests are not retried unless a policy is set.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Requests wait for capacity in the limiter before being sent. The
+    /// limiter may be shared with other clients.
+    pub fn rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Lets generate requests move large prefixes into context caches.
+    pub fn cache_manager(mut self, manager: CacheManager) -> Self {
+        self.cache_manager = Some(manager);
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let reqwest = match self.reqwest {
+            Some(client) => client,
+            None => {
+                let mut builder = reqwest::Client::builder().default_headers(self.default_headers);
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                if let Some(proxy) = self.proxy {
+                    builder = builder.proxy(proxy);
+                }
+                if let Some(user_agent) = self.user_agent {
+                    builder = builder.user_agent(&*user_agent);
+                }
+                builder.build()?
+            }
+        };
+
+        let inner = ClientInner {
+            reqwest,
+            credentials: self
+                .credentials
+                .unwrap_or_else(|| Box::new(Chain::from_env())),
+            base_url: self.base_url.unwrap_or_else(|| BASE_URI.into()),
+            api_version: self.api_version.unwrap_or_else(|| API_VERSION.into()),
+            retry_policy: self.retry_policy,
+            rate_limiter: self.rate_limiter,
+            cache_manager: self.cache_manager,
+        };
+
+        Ok(Client {
+            inner: Arc::new(inner),
+        })
+    }
+}
+
 pub struct ClientInner {
-    reqwest: reqwest::Client,
-    key: SecretString,
+    pub(crate) reqwest: reqwest::Client,
+    credentials: Box<dyn CredentialProvider>,
+    base_url: Box<str>,
+    api_version: Box<str>,
+    retry_policy: Option<RetryPolicy>,
+    rate_limiter: Option<RateLimiter>,
+    cache_manager: Option<CacheManager>,
 }
 
 impl ClientInner {
-    fn new(key: Option<SecretString>) -> Arc<Self> {
-        Self {
-            reqwest: reqwest::Client::new(),
-            key: key
-                .or_else(|| std::env::var("GEMINI_API_KEY").ok().map(Into::into))
-                .expect("API key must be set either via argument or GEMINI_API_KEY environment variable"),
+    /// Makes a single attempt, reporting the HTTP status alongside any error
+    /// so the retry policy can act on it.
+    pub(crate) async fn execute(
+        &self,
+        mut request: reqwest::Request,
+    ) -> std::result::Result<reqwest::Response, (Error, Option<u16>)> {
+        let key = async {
+            let mut key = reqwest::header::HeaderValue::from_str(
+                self.credentials.api_key().await?.expose_secret(),
+            )
+            .map_err(|_| Error::Credentials("API key is not a valid header value".into()))?;
+            key.set_sensitive(true);
+            Ok(key)
+        };
+        request
+            .headers_mut()
+            .insert(API_KEY_HEADER, key.await.map_err(|error| (error, None))?);
+
+        let response = self
+            .reqwest
+            .execute(request)
+            .await
+            .map_err(|error| (error.into(), None))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        if matches!(
+            status,
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN
+        ) {
+            self.credentials.invalidate();
         }
-        .into()
+
+        let code = Some(status.as_u16());
+        let headers = response.headers().clone();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|error| (error.into(), code))?;
+        let error = match serde_json::from_slice::<types::ApiError>(&body) {
+            Ok(api_error) => Error::Gemini {
+                status: status.as_u16(),
+                detail: api_error.error,
+            },
+            Err(_) => Error::HttpStatus {
+                status: status.as_u16(),
+                headers,
+                body: truncate(&String::from_utf8_lossy(&body), MAX_ERROR_BODY),
+            },
+        };
+        Err((error, code))
     }
 }
 
-pub trait Request: Send + Sized + 'static {
+/// How much of an unparseable error body is kept.
+const MAX_ERROR_BODY: usize = 4096;
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_owned();
+    }
+    let end = (0..=max)
+        .rev()
+        .find(|&end| text.is_char_boundary(end))
+        .unwrap_or(0);
+    format!("{}...", &text[..end])
+}
+
+/// Never prints the credentials, only the endpoint they are sent to.
+impl std::fmt::Debug for ClientInner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientInner")
+            .field("base_url", &self.base_url)
+            .field("api_version", &self.api_version)
+            .field("retry_policy", &self.retry_policy)
+            .field("rate_limiter", &self.rate_limiter)
+            .field("cache_manager", &self.cache_manager)
+            .finish_non_exhaustive()
+    }
+}
+
+pub trait Request: Endpoint {
     type Model: serde::de::DeserializeOwned + Send + 'static;
+
+    /// Prompt tokens the API reports having billed for this request.
+    fn prompt_tokens(_model: &Self::Model) -> Option<u64> {
+        None
+    }
+}
+
+pub trait Endpoint: Send + Sized + 'static {
     type Body: serde::ser::Serialize;
 
     const METHOD: Method;
 
+    /// Whether the request goes to the media upload endpoint rather than the
+    /// regular API.
+    const UPLOAD: bool = false;
+
     fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result;
 
+    fn headers(&self, _headers: &mut reqwest::header::HeaderMap) {}
+
+    /// The model the request is billed against, for rate limiting.
+    fn model(&self) -> Option<&str> {
+        None
+    }
+
+    /// The model and body a [`CacheManager`] may move a prefix out of.
+    fn cacheable(&mut self) -> Option<(Box<str>, &mut types::GenerateContent)> {
+        None
+    }
+
     fn body(self) -> Option<Self::Body> {
         None
     }
@@ -0,0 +1,329 @@
+use std::{
+    ffi::OsString,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use futures::{FutureExt as _, future::BoxFuture};
+use secrecy::{ExposeSecret as _, SecretString};
+
+use crate::{Error, Result};
+
+pub const API_KEY_VAR: &str = "GEMINI_API_KEY";
+pub const API_KEY_FILE_VAR: &str = "GEMINI_API_KEY_FILE";
+
+/// A source of API keys.
+///
+/// The provider is asked for a key on every request, so implementations are
+/// free to rotate keys without rebuilding the [`Client`](crate::Client).
+pub trait CredentialProvider: Send + Sync + 'static {
+    fn api_key(&self) -> BoxFuture<'_, Result<SecretString>>;
+
+    /// Called when the API rejects a key with 401 Unauthorized or 403
+    /// Forbidden, so that a cached key is fetched again. Does nothing by
+    /// default.
+    fn invalidate(&self) {}
+}
+
+/// A fixed key given up front.
+pub struct StaticKey {
+    key: SecretString,
+}
+
+impl StaticKey {
+    pub fn new(key: impl Into<SecretString>) -> Self {
+        Self { key: key.into() }
+    }
+}
+
+impl CredentialProvider for StaticKey {
+    fn api_key(&self) -> BoxFuture<'_, Result<SecretString>> {
+        futures::future::ready(Ok(self.key.clone())).boxed()
+    }
+}
+
+/// Reads the key from an environment variable.
+pub struct EnvVar {
+    name: Box<str>,
+}
+
+impl EnvVar {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl Default for EnvVar {
+    fn default() -> Self {
+        Self::new(API_KEY_VAR)
+    }
+}
+
+impl CredentialProvider for EnvVar {
+    fn api_key(&self) -> BoxFuture<'_, Result<SecretString>> {
+        let key = match std::env::var(&*self.name) {
+            Ok(key) => Ok(key.into()),
+            Err(_) => Err(Error::Credentials(format!("{} is not set", self.name))),
+        };
+        futures::future::ready(key).boxed()
+    }
+}
+
+/// Reads the key from a file, as Docker and Kubernetes secrets provide it.
+///
+/// Surrounding whitespace, such as a trailing newline, is ignored.
+pub struct KeyFile {
+    path: Option<PathBuf>,
+}
+
+impl KeyFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// Takes the path from `GEMINI_API_KEY_FILE` each time a key is needed.
+    pub fn from_env() -> Self {
+        Self { path: None }
+    }
+}
+
+impl CredentialProvider for KeyFile {
+    fn api_key(&self) -> BoxFuture<'_, Result<SecretString>> {
+        async move {
+            let path = match &self.path {
+                Some(path) => path.clone(),
+                None => std::env::var_os(API_KEY_FILE_VAR)
+                    .map(PathBuf::from)
+                    .ok_or_else(|| Error::Credentials(format!("{API_KEY_FILE_VAR} is not set")))?,
+            };
+
+            let contents = tokio::fs::read_to_string(&path).await.map_err(|error| {
+                Error::Credentials(format!("failed to read {}: {error}", path.display()))
+            })?;
+            non_empty(contents.trim(), || path.display().to_string())
+        }
+        .boxed()
+    }
+}
+
+/// Runs an external program and uses its standard output as the key.
+///
+/// The key is kept for [`Command::ttl`], and dropped early when the API
+/// rejects it, so the program runs once per TTL rather than once per request
+/// or upload chunk. Concurrent requests wait for a single run.
+pub struct Command {
+    program: OsString,
+    args: Vec<OsString>,
+    ttl: Duration,
+    cached: Mutex<Option<(SecretString, Instant)>>,
+    running: tokio::sync::Mutex<()>,
+}
+
+impl Command {
+    pub fn new(program: impl Into<OsString>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            ttl: Duration::from_secs(5 * 60),
+            cached: Mutex::new(None),
+            running: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// How long a key is used before the program runs again. Defaults to five
+    /// minutes; zero runs it for every request.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn cached(&self) -> Option<SecretString> {
+        let cached = self.lock();
+        let (key, expires_at) = cached.as_ref()?;
+        (Instant::now() < *expires_at).then(|| key.clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<(SecretString, Instant)>> {
+        self.cached
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    async fn run(&self) -> Result<SecretString> {
+        let program = self.program.to_string_lossy();
+        let output = tokio::process::Command::new(&self.program)
+            .args(&self.args)
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .output()
+            .await
+            .map_err(|error| Error::Credentials(format!("failed to run {program}: {error}")))?;
+
+        if !output.status.success() {
+            return Err(Error::Credentials(format!(
+                "{program} exited with {}",
+                output.status
+            )));
+        }
+
+        let stdout = String::from_utf8(output.stdout).map_err(|_| {
+            Error::Credentials(format!("{program} printed a key that is not UTF-8"))
+        })?;
+        non_empty(stdout.trim(), || program.into_owned())
+    }
+}
+
+impl CredentialProvider for Command {
+    fn api_key(&self) -> BoxFuture<'_, Result<SecretString>> {
+        async move {
+            if let Some(key) = self.cached() {
+                return Ok(key);
+            }
+
+            let _running = self.running.lock().await;
+            // Another request may have run the program while this one waited.
+            if let Some(key) = self.cached() {
+                return Ok(key);
+            }
+
+            let key = self.run().await?;
+            *self.lock() = Some((key.clone(), Instant::now() + self.ttl));
+            Ok(key)
+        }
+        .boxed()
+    }
+
+    fn invalidate(&self) {
+        *self.lock() = None;
+    }
+}
+
+/// Tries each provider in turn and uses the first key found.
+#[derive(Default)]
+pub struct Chain {
+    providers: Vec<Box<dyn CredentialProvider>>,
+}
+
+impl Chain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `GEMINI_API_KEY`, then the file named by `GEMINI_API_KEY_FILE`.
+    pub fn from_env() -> Self {
+        Self::new()
+            .with(EnvVar::default())
+            .with(KeyFile::from_env())
+    }
+
+    pub fn with(mut self, provider: impl CredentialProvider) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+}
+
+impl CredentialProvider for Chain {
+    fn api_key(&self) -> BoxFuture<'_, Result<SecretString>> {
+        async move {
+            let mut errors = Vec::new();
+            for provider in &self.providers {
+                match provider.api_key().await {
+                    Ok(key) => return Ok(key),
+                    Err(Error::Credentials(message)) => errors.push(message),
+                    Err(error) => errors.push(error.to_string()),
+                }
+            }
+
+            Err(Error::Credentials(if errors.is_empty() {
+                "no credential providers configured".into()
+            } else {
+                format!("no API key found ({})", errors.join("; "))
+            }))
+        }
+        .boxed()
+    }
+
+    fn invalidate(&self) {
+        for provider in &self.providers {
+            provider.invalidate();
+        }
+    }
+}
+
+fn non_empty(key: &str, source: impl FnOnce() -> String) -> Result<SecretString> {
+    let key = SecretString::from(key);
+    if key.expose_secret().is_empty() {
+        return Err(Error::Credentials(format!("{} is empty", source())));
+    }
+    Ok(key)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// A command that prints a key and appends a line to a file each time it
+    /// runs, returned with a way to count those runs.
+    fn counting_command(name: &str) -> (Command, impl Fn() -> usize) {
+        let log = std::env::temp_dir().join(format!("gemini-rs-{name}-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&log);
+        let command = Command::new("sh")
+            .arg("-c")
+            .arg(r#"echo run >> "$0"; echo secret-key"#)
+            .arg(&log);
+        let runs = move || {
+            std::fs::read_to_string(&log)
+                .map(|log| log.lines().count())
+                .unwrap_or(0)
+        };
+        (command, runs)
+    }
+
+    #[tokio::test]
+    async fn command_key_is_cached() {
+        let (command, runs) = counting_command("cached");
+
+        for _ in 0..3 {
+            let key = command.api_key().await.unwrap();
+            assert_eq!(key.expose_secret(), "secret-key");
+        }
+        let keys = futures::future::join_all((0..4).map(|_| command.api_key())).await;
+        assert!(keys.iter().all(Result::is_ok));
+        assert_eq!(runs(), 1);
+
+        command.invalidate();
+        command.api_key().await.unwrap();
+        assert_eq!(runs(), 2);
+    }
+
+    #[tokio::test]
+    async fn command_key_expires() {
+        let (command, runs) = counting_command("expires");
+        let command = command.ttl(Duration::ZERO);
+
+        command.api_key().await.unwrap();
+        command.api_key().await.unwrap();
+        assert_eq!(runs(), 2);
+    }
+
+    #[tokio::test]
+    async fn failed_command_is_not_cached() {
+        let command = Command::new("sh").arg("-c").arg("exit 3");
+
+        for _ in 0..2 {
+            let error = command.api_key().await.unwrap_err();
+            assert!(
+                matches!(&error, Error::Credentials(message) if message.contains("exit status: 3")),
+                "{error:?}"
+            );
+        }
+    }
+}
@@ -4,6 +4,116 @@ pub enum Error {
     Serde(#[from] serde_json::Error),
     #[error("http: {0}")]
     Http(#[from] reqwest::Error),
-    #[error("gemini: {0:?}")]
-    Gemini(crate::types::ErrorDetail),
+    #[error("credentials: {0}")]
+    Credentials(String),
+    #[error("file {name} failed to process: {message}")]
+    FileFailed { name: String, message: String },
+    #[error("file {name} was still {state:?} after {timeout:?}")]
+    FileNotReady {
+        name: String,
+        state: crate::types::FileState,
+        timeout: std::time::Duration,
+    },
+    #[error("upload: {0}")]
+    Upload(String),
+    #[error("the model kept calling functions after {0} rounds")]
+    MaxToolSteps(usize),
+    #[error("response does not match the schema: {}", join(.0))]
+    Schema(Vec<crate::SchemaViolation>),
+    #[error("json schema at {path}: {message}")]
+    JsonSchema { path: String, message: String },
+    #[error("prompt blocked: {reason:?}")]
+    PromptBlocked {
+        reason: crate::types::BlockReason,
+        safety_ratings: Vec<crate::types::SafetyRating>,
+    },
+    #[error("candidate finished without text: {finish_reason:?}")]
+    CandidateFinished {
+        finish_reason: crate::types::FinishReason,
+    },
+    #[error("gemini ({status}): {detail:?}")]
+    Gemini {
+        /// The HTTP status of the response the error arrived in.
+        status: u16,
+        detail: crate::types::ErrorDetail,
+    },
+    /// A failed response whose body is not a `google.rpc.Status`, such as an
+    /// HTML page from a proxy or an empty body.
+    #[error("http status {status}: {}", if body.is_empty() { "empty body" } else { body })]
+    HttpStatus {
+        status: u16,
+        headers: reqwest::header::HeaderMap,
+        /// The start of the body, as text.
+        body: String,
+    },
+    /// A successful response whose body could not be parsed, such as a
+    /// truncated one or one that does not match the expected type.
+    #[error("http status {status}: invalid body: {source}")]
+    Decode {
+        status: u16,
+        /// The start of the body, as text.
+        body: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+impl Error {
+    /// The HTTP status of the response this error came from, if any.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Gemini { status, .. }
+            | Self::HttpStatus { status, .. }
+            | Self::Decode { status, .. } => Some(*status),
+            Self::Http(error) => error.status().map(|status| status.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// How long the server asked to wait before retrying, from a
+    /// `google.rpc.RetryInfo` detail or a `Retry-After` header in seconds.
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            Self::Gemini { detail, .. } => detail.retry_delay(),
+            Self::HttpStatus { headers, .. } => headers
+                .get(reqwest::header::RETRY_AFTER)?
+                .to_str()
+                .ok()?
+                .trim()
+                .parse()
+                .ok()
+                .map(std::time::Duration::from_secs),
+            _ => None,
+        }
+    }
+
+    /// The quotas the request exceeded, from a `google.rpc.QuotaFailure`
+    /// detail.
+    pub fn quota_violations(&self) -> impl Iterator<Item = &crate::types::QuotaViolation> {
+        match self {
+            Self::Gemini { detail, .. } => Some(detail),
+            _ => None,
+        }
+        .into_iter()
+        .flat_map(|detail| detail.quota_violations())
+    }
+
+    /// The id a server or proxy assigned to the failed request, for support
+    /// requests.
+    pub fn request_id(&self) -> Option<&str> {
+        let Self::HttpStatus { headers, .. } = self else {
+            return None;
+        };
+        ["x-request-id", "x-goog-request-id", "x-cloud-trace-context"]
+            .iter()
+            .find_map(|name| headers.get(*name)?.to_str().ok())
+    }
+}
+
+fn join(violations: &[crate::SchemaViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
 }
@@ -0,0 +1,423 @@
+use futures::FutureExt as _;
+use reqwest::{
+    Method,
+    header::{CONTENT_LENGTH, HeaderMap, HeaderValue},
+};
+use tokio::io::{AsyncRead, AsyncReadExt as _};
+
+use crate::{
+    Client, Error, Result,
+    client::{Endpoint, Formatter, Request, Route},
+    types,
+};
+
+const UPLOAD_PROTOCOL_HEADER: &str = "x-goog-upload-protocol";
+const UPLOAD_COMMAND_HEADER: &str = "x-goog-upload-command";
+const UPLOAD_OFFSET_HEADER: &str = "x-goog-upload-offset";
+const UPLOAD_URL_HEADER: &str = "x-goog-upload-url";
+const UPLOAD_STATUS_HEADER: &str = "x-goog-upload-status";
+const UPLOAD_SIZE_RECEIVED_HEADER: &str = "x-goog-upload-size-received";
+
+const CHUNK_GRANULARITY: usize = 256 * 1024;
+const DEFAULT_CHUNK_SIZE: usize = 32 * CHUNK_GRANULARITY;
+
+/// Uploads a file through the resumable upload protocol: a `start` request
+/// announces the file and returns a session URL, to which the bytes are then
+/// sent in chunks.
+///
+/// If sending a chunk fails, the offset the server has actually received is
+/// queried and the upload continues from there.
+pub struct UploadFile {
+    reader: Box<dyn AsyncRead + Send + Unpin>,
+    size: u64,
+    mime_type: Box<str>,
+    display_name: Option<Box<str>>,
+    chunk_size: usize,
+    max_resumes: u32,
+    session_url: Option<Box<str>>,
+    on_progress: Option<ProgressCallback>,
+}
+
+type ProgressCallback = Box<dyn Fn(&UploadProgress<'_>) + Send + Sync>;
+
+/// Passed to the [`UploadFile::on_progress`] callback after every chunk.
+#[derive(Debug)]
+pub struct UploadProgress<'a> {
+    /// Can be given to [`UploadFile::resume`] to continue the upload later.
+    pub session_url: &'a str,
+    pub uploaded: u64,
+    pub total: u64,
+}
+
+impl UploadFile {
+    pub fn new(data: Vec<u8>, mime_type: &str) -> Self {
+        let size = data.len() as u64;
+        Self::from_reader(std::io::Cursor::new(data), size, mime_type)
+    }
+
+    /// Streams `size` bytes from `reader` without holding more than one chunk
+    /// in memory.
+    pub fn from_reader(
+        reader: impl AsyncRead + Send + Unpin + 'static,
+        size: u64,
+        mime_type: &str,
+    ) -> Self {
+        Self {
+            reader: Box::new(reader),
+            size,
+            mime_type: mime_type.into(),
+            display_name: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_resumes: 3,
+            session_url: None,
+            on_progress: None,
+        }
+    }
+
+    pub fn display_name(&mut self, name: &str) {
+        self.display_name = Some(name.into());
+    }
+
+    /// Rounded up to a multiple of 256 KiB, as the protocol requires. Defaults
+    /// to 8 MiB.
+    pub fn chunk_size(&mut self, size: usize) {
+        self.chunk_size = size.max(1).next_multiple_of(CHUNK_GRANULARITY);
+    }
+
+    /// How many times a failed chunk is resumed before giving up.
+    pub fn max_resumes(&mut self, resumes: u32) {
+        self.max_resumes = resumes;
+    }
+
+    pub fn on_progress(&mut self, callback: impl Fn(&UploadProgress<'_>) + Send + Sync + 'static) {
+        self.on_progress = Some(Box::new(callback));
+    }
+
+    /// Continues an interrupted session instead of starting a new one.
+    ///
+    /// The reader must be positioned at the start of the file; the bytes the
+    /// server already has are skipped.
+    pub fn resume(&mut self, session_url: &str) {
+        self.session_url = Some(session_url.into());
+    }
+
+    fn start(&self) -> StartUpload {
+        StartUpload {
+            size: self.size,
+            mime_type: self.mime_type.clone(),
+            display_name: self.display_name.clone(),
+        }
+    }
+
+    async fn upload(mut self, client: &Client, session_url: &str) -> Result<types::File> {
+        let mut offset = 0;
+        if self.session_url.is_some() {
+            match query(client, session_url).await? {
+                Query::Active(received) => offset = received,
+                Query::Final(file) => return Ok(*file),
+            }
+            self.skip(offset).await?;
+        }
+
+        let mut chunk = Vec::new();
+        let mut resumes = 0;
+        loop {
+            let chunk_start = offset;
+            let expected = self
+                .size
+                .saturating_sub(chunk_start)
+                .min(self.chunk_size as u64);
+            chunk.clear();
+            (&mut self.reader)
+                .take(expected)
+                .read_to_end(&mut chunk)
+                .await
+                .map_err(|error| Error::Upload(format!("failed to read file: {error}")))?;
+
+            let chunk_end = chunk_start + chunk.len() as u64;
+            if (chunk.len() as u64) < expected {
+                return Err(Error::Upload(format!(
+                    "reader ended after {chunk_end} of {} bytes",
+                    self.size
+                )));
+            }
+            let finalize = chunk_end >= self.size;
+
+            // An empty final chunk still has to be sent to finalize the upload.
+            while offset < chunk_end || (finalize && chunk.is_empty()) {
+                let data = chunk[(offset - chunk_start) as usize..].to_vec();
+                let command = if finalize {
+                    "upload, finalize"
+                } else {
+                    "upload"
+                };
+                let request = client
+                    .reqwest
+                    .post(session_url)
+                    .header(CONTENT_LENGTH, data.len())
+                    .header(UPLOAD_OFFSET_HEADER, offset)
+                    .header(UPLOAD_COMMAND_HEADER, command)
+                    .body(data)
+                    .build()?;
+
+                let (error, http_code) = match client.execute(request).await {
+                    Ok(response) if finalize => {
+                        self.progress(session_url, self.size);
+                        return Ok(response.json::<types::FileEnvelope>().await?.file);
+                    }
+                    Ok(_) => {
+                        offset = chunk_end;
+                        continue;
+                    }
+                    Err(failure) => failure,
+                };
+
+                let resumable = match &error {
+                    Error::Http(error) => !error.is_builder(),
+                    _ => http_code.is_some_and(|code| code >= 500),
+                };
+                if !resumable || resumes >= self.max_resumes {
+                    return Err(error);
+                }
+                resumes += 1;
+
+                match query(client, session_url).await? {
+                    Query::Final(file) => return Ok(*file),
+                    Query::Active(received) if (chunk_start..=chunk_end).contains(&received) => {
+                        offset = received;
+                    }
+                    Query::Active(received) => {
+                        return Err(Error::Upload(format!(
+                            "server reports {received} bytes received, \
+                             outside of the chunk at {chunk_start}..{chunk_end}"
+                        )));
+                    }
+                }
+            }
+
+            self.progress(session_url, offset);
+        }
+    }
+
+    async fn skip(&mut self, bytes: u64) -> Result<()> {
+        let skipped = tokio::io::copy(&mut (&mut self.reader).take(bytes), &mut tokio::io::sink())
+            .await
+            .map_err(|error| Error::Upload(format!("failed to read file: {error}")))?;
+        if skipped < bytes {
+            return Err(Error::Upload(format!(
+                "reader ended after {skipped} of {bytes} already uploaded bytes"
+            )));
+        }
+        Ok(())
+    }
+
+    fn progress(&self, session_url: &str, uploaded: u64) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(&UploadProgress {
+                session_url,
+                uploaded,
+                total: self.size,
+            });
+        }
+    }
+}
+
+enum Query {
+    Active(u64),
+    Final(Box<types::File>),
+}
+
+/// Asks the server how much of the upload it has received.
+async fn query(client: &Client, session_url: &str) -> Result<Query> {
+    let request = client
+        .reqwest
+        .post(session_url)
+        .header(CONTENT_LENGTH, 0)
+        .header(UPLOAD_COMMAND_HEADER, "query")
+        .build()?;
+    let response = client.execute(request).await.map_err(|(error, _)| error)?;
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+    };
+    if header(UPLOAD_STATUS_HEADER) == Some("final") {
+        let file = response.json::<types::FileEnvelope>().await?.file;
+        return Ok(Query::Final(Box::new(file)));
+    }
+
+    header(UPLOAD_SIZE_RECEIVED_HEADER)
+        .and_then(|received| received.parse().ok())
+        .map(Query::Active)
+        .ok_or_else(|| Error::Upload(format!("response has no {UPLOAD_SIZE_RECEIVED_HEADER}")))
+}
+
+impl IntoFuture for Route<UploadFile> {
+    type Output = Result<types::File>;
+    type IntoFuture = futures::future::BoxFuture<'static, Self::Output>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        async move {
+            let client = self.client().clone();
+            let mut upload = None;
+            let start = self.map(|kind| {
+                let start = kind.start();
+                upload = Some(kind);
+                start
+            });
+            let upload = upload.expect("map always calls its closure");
+
+            let session_url = match &upload.session_url {
+                Some(session_url) => session_url.to_string(),
+                None => {
+                    let (response, _) = start.send().await?;
+                    response
+                        .headers()
+                        .get(UPLOAD_URL_HEADER)
+                        .and_then(|url| url.to_str().ok())
+                        .ok_or_else(|| {
+                            Error::Upload(format!("response has no {UPLOAD_URL_HEADER}"))
+                        })?
+                        .to_owned()
+                }
+            };
+
+            upload.upload(&client, &session_url).await
+        }
+        .boxed()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct StartUpload {
+    size: u64,
+    mime_type: Box<str>,
+    display_name: Option<Box<str>>,
+}
+
+impl Endpoint for StartUpload {
+    type Body = types::FileEnvelope;
+
+    const METHOD: Method = Method::POST;
+    const UPLOAD: bool = true;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("files")
+    }
+
+    fn headers(&self, headers: &mut HeaderMap) {
+        headers.insert(
+            UPLOAD_PROTOCOL_HEADER,
+            HeaderValue::from_static("resumable"),
+        );
+        headers.insert(UPLOAD_COMMAND_HEADER, HeaderValue::from_static("start"));
+        headers.insert("x-goog-upload-header-content-length", self.size.into());
+        if let Ok(mime_type) = HeaderValue::from_str(&self.mime_type) {
+            headers.insert("x-goog-upload-header-content-type", mime_type);
+        }
+    }
+
+    fn body(self) -> Option<Self::Body> {
+        Some(types::FileEnvelope {
+            file: types::File {
+                display_name: self.display_name.map(Into::into),
+                mime_type: self.mime_type.into(),
+                ..Default::default()
+            },
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct GetFile {
+    name: Box<str>,
+}
+
+impl GetFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: file_id(name).into(),
+        }
+    }
+}
+
+impl Request for GetFile {
+    type Model = types::File;
+}
+
+impl Endpoint for GetFile {
+    type Body = ();
+
+    const METHOD: Method = Method::GET;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("files/")?;
+        fmt.write_str(&self.name)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ListFiles {
+    page_size: Option<usize>,
+    page_token: Option<Box<str>>,
+}
+
+impl ListFiles {
+    pub fn page_size(&mut self, size: usize) {
+        self.page_size = size.into();
+    }
+
+    pub fn page_token(&mut self, token: &str) {
+        self.page_token = Some(Box::from(token));
+    }
+}
+
+impl Request for ListFiles {
+    type Model = types::Files;
+}
+
+impl Endpoint for ListFiles {
+    type Body = ();
+
+    const METHOD: Method = Method::GET;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("files")?;
+        fmt.write_optional_query_param("pageSize", self.page_size.as_ref())?;
+        fmt.write_optional_query_param("pageToken", self.page_token.as_ref())
+    }
+}
+
+#[derive(Debug)]
+pub struct DeleteFile {
+    name: Box<str>,
+}
+
+impl DeleteFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: file_id(name).into(),
+        }
+    }
+}
+
+impl Request for DeleteFile {
+    type Model = types::Empty;
+}
+
+impl Endpoint for DeleteFile {
+    type Body = ();
+
+    const METHOD: Method = Method::DELETE;
+
+    fn format_uri(&self, fmt: &mut Formatter<'_, '_>) -> std::fmt::Result {
+        fmt.write_str("files/")?;
+        fmt.write_str(&self.name)
+    }
+}
+
+fn file_id(name: &str) -> &str {
+    name.strip_prefix("files/").unwrap_or(name)
+}
@@ -0,0 +1,671 @@
+use serde_json::Value;
+
+use crate::{
+    Error, Result,
+    types::{Schema, Type},
+};
+
+/// A [`Schema`] converted from a JSON Schema document, along with what was
+/// dropped on the way.
+#[derive(Debug, Clone)]
+pub struct JsonSchemaConversion {
+    pub schema: Schema,
+    /// Keywords Gemini has no equivalent for and that were left out, each
+    /// prefixed with the JSON pointer of the schema it appeared in. Dropping
+    /// them makes the schema more permissive, but never rejects valid output.
+    pub warnings: Vec<String>,
+}
+
+/// Keywords that only document a schema.
+const ANNOTATIONS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$anchor",
+    "$comment",
+    "$defs",
+    "definitions",
+    "title",
+    "default",
+    "examples",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+    "contentEncoding",
+    "contentMediaType",
+];
+
+/// Keywords that restrict values in ways Gemini cannot express.
+const CONSTRAINTS: &[&str] = &[
+    "pattern",
+    "minLength",
+    "maxLength",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "multipleOf",
+    "uniqueItems",
+    "contains",
+    "minContains",
+    "maxContains",
+    "minProperties",
+    "maxProperties",
+    "additionalProperties",
+    "unevaluatedProperties",
+    "unevaluatedItems",
+    "propertyNames",
+    "dependentRequired",
+];
+
+/// Keywords that change the shape of a value, which cannot be dropped.
+const UNSUPPORTED: &[&str] = &[
+    "not",
+    "if",
+    "then",
+    "else",
+    "prefixItems",
+    "additionalItems",
+    "patternProperties",
+    "dependentSchemas",
+    "dependencies",
+    "$dynamicRef",
+    "$recursiveRef",
+];
+
+const FORMATS: &[&str] = &["enum", "date-time", "int32", "int64", "float", "double"];
+
+impl Schema {
+    /// Converts a JSON Schema document, such as the output of `schemars`.
+    ///
+    /// Local `$ref`s are inlined, a `null` in `type`, `enum`, `anyOf` or
+    /// `oneOf` becomes `nullable`, and `allOf` is merged into a single schema.
+    /// Recursive references, remote references, and keywords that change the
+    /// shape of a value, such as `not` or an `anyOf` of several types, fail
+    /// with [`Error::JsonSchema`]. Validation keywords Gemini does not support
+    /// are dropped and reported in [`JsonSchemaConversion::warnings`].
+    pub fn from_json_schema(document: &Value) -> Result<JsonSchemaConversion> {
+        let mut converter = Converter {
+            root: document,
+            refs: Vec::new(),
+            warnings: Vec::new(),
+        };
+        let schema = converter.convert(document, "#")?;
+        Ok(JsonSchemaConversion {
+            schema,
+            warnings: converter.warnings,
+        })
+    }
+}
+
+struct Converter<'a> {
+    root: &'a Value,
+    /// The references being inlined, to catch recursion.
+    refs: Vec<&'a str>,
+    warnings: Vec<String>,
+}
+
+impl<'a> Converter<'a> {
+    fn convert(&mut self, schema: &'a Value, path: &str) -> Result<Schema> {
+        let object = match schema {
+            Value::Object(object) => object,
+            Value::Bool(true) => return Ok(Schema::default()),
+            Value::Bool(false) => return Err(error(path, "`false` schemas are not supported")),
+            _ => return Err(error(path, "expected a schema object")),
+        };
+
+        let mut own = Schema::default();
+        for (keyword, value) in object {
+            let at = format!("{path}/{}", escape(keyword));
+            match keyword.as_str() {
+                "$ref" | "allOf" | "anyOf" | "oneOf" => {}
+                "type" => self.apply_type(&mut own, value, &at)?,
+                "nullable" => own.nullable = value.as_bool().filter(|&nullable| nullable),
+                "description" => own.description = value.as_str().map(Into::into),
+                "format" => match value.as_str() {
+                    Some(format) if FORMATS.contains(&format) => own.format = Some(format.into()),
+                    _ => self.warn(
+                        path,
+                        format!("format {value} is not supported and was dropped"),
+                    ),
+                },
+                "enum" => apply_enum(&mut own, value, &at)?,
+                "const" => apply_enum(&mut own, &Value::Array(vec![value.clone()]), &at)?,
+                "items" => match value {
+                    Value::Array(_) => {
+                        return Err(error(&at, "tuple `items` are not supported"));
+                    }
+                    _ => own.items = Some(Box::new(self.convert(value, &at)?)),
+                },
+                "minItems" => own.min_items = Some(count(value, &at)?),
+                "maxItems" => own.max_items = Some(count(value, &at)?),
+                "properties" => {
+                    let Value::Object(properties) = value else {
+                        return Err(error(&at, "expected an object"));
+                    };
+                    let mut converted = std::collections::BTreeMap::new();
+                    for (name, property) in properties {
+                        let property_path = format!("{at}/{}", escape(name));
+                        converted.insert(name.clone(), self.convert(property, &property_path)?);
+                    }
+                    own.properties = Some(converted);
+                }
+                "required" => {
+                    let required = value
+                        .as_array()
+                        .and_then(|names| {
+                            names
+                                .iter()
+                                .map(|name| name.as_str().map(String::from))
+                                .collect::<Option<Vec<_>>>()
+                        })
+                        .ok_or_else(|| error(&at, "expected an array of property names"))?;
+                    own.required = Some(required);
+                }
+                keyword if ANNOTATIONS.contains(&keyword) => {}
+                keyword if CONSTRAINTS.contains(&keyword) => {
+                    self.warn(
+                        path,
+                        format!("`{keyword}` is not supported and was dropped"),
+                    );
+                }
+                keyword if UNSUPPORTED.contains(&keyword) => {
+                    return Err(error(&at, format!("`{keyword}` is not supported")));
+                }
+                keyword => self.warn(path, format!("unknown keyword `{keyword}` was dropped")),
+            }
+        }
+
+        for keyword in ["anyOf", "oneOf"] {
+            if let Some(variants) = object.get(keyword) {
+                let variant = self.convert_nullable(variants, &format!("{path}/{keyword}"))?;
+                own = merge(own, variant, path)?;
+            }
+        }
+
+        if let Some(all_of) = object.get("allOf") {
+            let Value::Array(schemas) = all_of else {
+                return Err(error(&format!("{path}/allOf"), "expected an array"));
+            };
+            for (index, schema) in schemas.iter().enumerate() {
+                let schema = self.convert(schema, &format!("{path}/allOf/{index}"))?;
+                own = merge(own, schema, path)?;
+            }
+        }
+
+        if let Some(reference) = object.get("$ref") {
+            let target = self.resolve(reference, &format!("{path}/$ref"))?;
+            own = merge(own, target, path)?;
+        }
+
+        Ok(own)
+    }
+
+    /// Converts `[T, {"type": "null"}]`, the one `anyOf` or `oneOf` shape
+    /// Gemini can express.
+    fn convert_nullable(&mut self, variants: &'a Value, path: &str) -> Result<Schema> {
+        let Value::Array(variants) = variants else {
+            return Err(error(path, "expected an array"));
+        };
+
+        let is_null = |variant: &Value| variant.get("type").and_then(Value::as_str) == Some("null");
+        let nullable = variants.iter().any(is_null);
+        let mut others = variants.iter().enumerate().filter(|(_, v)| !is_null(v));
+
+        match (others.next(), others.next()) {
+            (Some((index, variant)), None) => {
+                let mut schema = self.convert(variant, &format!("{path}/{index}"))?;
+                if nullable {
+                    schema.nullable = Some(true);
+                }
+                Ok(schema)
+            }
+            (None, _) => Err(error(path, "expected a variant other than null")),
+            (Some(_), Some(_)) => Err(error(
+                path,
+                "several alternatives are not supported, only one and null",
+            )),
+        }
+    }
+
+    fn resolve(&mut self, reference: &'a Value, path: &str) -> Result<Schema> {
+        let Some(reference) = reference.as_str() else {
+            return Err(error(path, "expected a string"));
+        };
+        let Some(pointer) = reference.strip_prefix('#') else {
+            return Err(error(
+                path,
+                format!("only local references are supported, not {reference:?}"),
+            ));
+        };
+        if self.refs.contains(&reference) {
+            return Err(error(
+                path,
+                format!("recursive reference {reference:?} is not supported"),
+            ));
+        }
+        let Some(target) = self.root.pointer(pointer) else {
+            return Err(error(path, format!("{reference:?} does not resolve")));
+        };
+
+        self.refs.push(reference);
+        let schema = self.convert(target, reference);
+        self.refs.pop();
+        schema
+    }
+
+    fn apply_type(&mut self, schema: &mut Schema, value: &Value, path: &str) -> Result<()> {
+        let names = match value {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names
+                .iter()
+                .map(Value::as_str)
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| error(path, "expected type names"))?,
+            _ => return Err(error(path, "expected a type name or an array of them")),
+        };
+
+        let mut types = names.iter().filter(|&&name| name != "null");
+        let schema_type = match (types.next(), types.next()) {
+            (Some(name), None) => {
+                parse_type(name).ok_or_else(|| error(path, format!("unknown type {name:?}")))?
+            }
+            (None, _) => return Err(error(path, "`null` alone is not supported")),
+            (Some(_), Some(_)) => {
+                return Err(error(
+                    path,
+                    "several types are not supported, only one and null",
+                ));
+            }
+        };
+
+        schema.schema_type = Some(schema_type);
+        if names.contains(&"null") {
+            schema.nullable = Some(true);
+        }
+        Ok(())
+    }
+
+    fn warn(&mut self, path: &str, message: String) {
+        self.warnings.push(format!("{path}: {message}"));
+    }
+}
+
+fn apply_enum(schema: &mut Schema, value: &Value, path: &str) -> Result<()> {
+    let Value::Array(values) = value else {
+        return Err(error(path, "expected an array"));
+    };
+
+    let mut strings = Vec::new();
+    for value in values {
+        match value {
+            Value::String(string) => strings.push(string.clone()),
+            Value::Null => schema.nullable = Some(true),
+            _ => return Err(error(path, "only string values are supported")),
+        }
+    }
+
+    schema.schema_type.get_or_insert(Type::String);
+    schema.format.get_or_insert_with(|| "enum".into());
+    schema.enum_values = Some(strings);
+    Ok(())
+}
+
+fn parse_type(name: &str) -> Option<Type> {
+    Some(match name {
+        "object" => Type::Object,
+        "array" => Type::Array,
+        "string" => Type::String,
+        "integer" => Type::Integer,
+        "number" => Type::Number,
+        "boolean" => Type::Boolean,
+        _ => return None,
+    })
+}
+
+fn count(value: &Value, path: &str) -> Result<String> {
+    value
+        .as_u64()
+        .map(|count| count.to_string())
+        .ok_or_else(|| error(path, "expected a non-negative integer"))
+}
+
+/// Merges two schemas a value must both match. Where they disagree on an
+/// annotation, such as the description, `first` wins.
+fn merge(first: Schema, second: Schema, path: &str) -> Result<Schema> {
+    let accepts_null =
+        |schema: &Schema| schema.nullable == Some(true) || schema.schema_type.is_none();
+    let nullable = (accepts_null(&first)
+        && accepts_null(&second)
+        && (first.nullable == Some(true) || second.nullable == Some(true)))
+    .then_some(true);
+
+    let schema_type = match (first.schema_type, second.schema_type) {
+        (Some(a), Some(b)) if a != b => {
+            return Err(error(
+                path,
+                format!("conflicting types {a:?} and {b:?} cannot be merged"),
+            ));
+        }
+        (a, b) => a.or(b),
+    };
+
+    let enum_values = match (first.enum_values, second.enum_values) {
+        (Some(a), Some(b)) => Some(a.into_iter().filter(|value| b.contains(value)).collect()),
+        (a, b) => a.or(b),
+    };
+
+    let bound = |a: Option<String>, b: Option<String>, pick: fn(u64, u64) -> u64| match (a, b) {
+        (Some(a), Some(b)) => match (a.parse(), b.parse()) {
+            (Ok(x), Ok(y)) => Some(pick(x, y).to_string()),
+            _ => Some(a),
+        },
+        (a, b) => a.or(b),
+    };
+
+    let properties = match (first.properties, second.properties) {
+        (Some(mut a), Some(b)) => {
+            for (name, schema) in b {
+                let merged = match a.remove(&name) {
+                    Some(existing) => merge(existing, schema, path)?,
+                    None => schema,
+                };
+                a.insert(name, merged);
+            }
+            Some(a)
+        }
+        (a, b) => a.or(b),
+    };
+
+    let required = match (first.required, second.required) {
+        (Some(mut a), Some(b)) => {
+            for name in b {
+                if !a.contains(&name) {
+                    a.push(name);
+                }
+            }
+            Some(a)
+        }
+        (a, b) => a.or(b),
+    };
+
+    let items = match (first.items, second.items) {
+        (Some(a), Some(b)) => Some(Box::new(merge(*a, *b, path)?)),
+        (a, b) => a.or(b),
+    };
+
+    Ok(Schema {
+        schema_type,
+        format: first.format.or(second.format),
+        description: first.description.or(second.description),
+        nullable,
+        enum_values,
+        max_items: bound(first.max_items, second.max_items, u64::min),
+        min_items: bound(first.min_items, second.min_items, u64::max),
+        properties,
+        required,
+        property_ordering: first.property_ordering.or(second.property_ordering),
+        items,
+    })
+}
+
+/// Escapes a key for use in a JSON pointer.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn error(path: &str, message: impl Into<String>) -> Error {
+    Error::JsonSchema {
+        path: path.into(),
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn convert(document: Value) -> JsonSchemaConversion {
+        Schema::from_json_schema(&document).unwrap()
+    }
+
+    /// The path and message of the error converting `document` fails with.
+    fn failure(document: Value) -> (String, String) {
+        match Schema::from_json_schema(&document) {
+            Err(Error::JsonSchema { path, message }) => (path, message),
+            other => panic!("expected a JSON schema error, got {other:?}"),
+        }
+    }
+
+    fn property<'a>(schema: &'a Schema, name: &str) -> &'a Schema {
+        &schema.properties.as_ref().unwrap()[name]
+    }
+
+    #[test]
+    fn refs_into_defs_are_inlined() {
+        let conversion = convert(json!({
+            "type": "object",
+            "properties": {"home": {"$ref": "#/$defs/Address"}},
+            "$defs": {
+                "Address": {
+                    "type": "object",
+                    "properties": {"city": {"type": "string"}},
+                    "required": ["city"],
+                },
+            },
+        }));
+
+        let home = property(&conversion.schema, "home");
+        assert_eq!(home.schema_type, Some(Type::Object));
+        assert_eq!(home.required.as_deref(), Some(&["city".to_owned()][..]));
+        assert_eq!(property(home, "city").schema_type, Some(Type::String));
+        assert!(conversion.warnings.is_empty());
+    }
+
+    #[test]
+    fn recursive_refs_are_rejected() {
+        let (path, message) = failure(json!({
+            "$ref": "#/$defs/Node",
+            "$defs": {
+                "Node": {
+                    "type": "object",
+                    "properties": {"next": {"$ref": "#/$defs/Node"}},
+                },
+            },
+        }));
+        assert_eq!(path, "#/$defs/Node/properties/next/$ref");
+        assert_eq!(
+            message,
+            r##"recursive reference "#/$defs/Node" is not supported"##
+        );
+    }
+
+    #[test]
+    fn the_same_ref_may_appear_twice() {
+        let conversion = convert(json!({
+            "type": "object",
+            "properties": {
+                "from": {"$ref": "#/$defs/Point"},
+                "to": {"$ref": "#/$defs/Point"},
+            },
+            "$defs": {"Point": {"type": "array", "items": {"type": "number"}}},
+        }));
+        assert_eq!(
+            property(&conversion.schema, "to").schema_type,
+            Some(Type::Array)
+        );
+    }
+
+    #[test]
+    fn null_in_type_becomes_nullable() {
+        let schema = convert(json!({"type": ["integer", "null"]})).schema;
+        assert_eq!(schema.schema_type, Some(Type::Integer));
+        assert_eq!(schema.nullable, Some(true));
+
+        let (path, message) = failure(json!({"type": ["integer", "string"]}));
+        assert_eq!(path, "#/type");
+        assert_eq!(
+            message,
+            "several types are not supported, only one and null"
+        );
+    }
+
+    #[test]
+    fn any_of_a_ref_and_null_becomes_nullable() {
+        let schema = convert(json!({
+            "anyOf": [{"$ref": "#/$defs/Color"}, {"type": "null"}],
+            "$defs": {"Color": {"type": "string", "enum": ["red", "green"]}},
+        }))
+        .schema;
+        assert_eq!(schema.schema_type, Some(Type::String));
+        assert_eq!(schema.format.as_deref(), Some("enum"));
+        assert_eq!(schema.nullable, Some(true));
+
+        let (path, message) = failure(json!({"oneOf": [{"type": "string"}, {"type": "integer"}]}));
+        assert_eq!(path, "#/oneOf");
+        assert_eq!(
+            message,
+            "several alternatives are not supported, only one and null"
+        );
+    }
+
+    #[test]
+    fn all_of_is_merged() {
+        let schema = convert(json!({
+            "allOf": [
+                {"type": "object", "properties": {"a": {"type": "string"}}, "required": ["a"]},
+                {"properties": {"b": {"type": "integer"}}, "required": ["b"]},
+            ],
+        }))
+        .schema;
+        assert_eq!(schema.schema_type, Some(Type::Object));
+        assert_eq!(schema.required, Some(vec!["a".to_owned(), "b".to_owned()]));
+        assert_eq!(property(&schema, "b").schema_type, Some(Type::Integer));
+    }
+
+    #[test]
+    fn all_of_conflicts_are_rejected() {
+        let (path, message) = failure(json!({
+            "type": "object",
+            "properties": {"id": {"allOf": [{"type": "string"}, {"type": "integer"}]}},
+        }));
+        assert_eq!(path, "#/properties/id");
+        assert_eq!(
+            message,
+            "conflicting types String and Integer cannot be merged"
+        );
+    }
+
+    #[test]
+    fn constraints_are_dropped_with_a_warning() {
+        let conversion = convert(json!({
+            "type": "object",
+            "properties": {
+                "code": {"type": "string", "pattern": "^[A-Z]+$", "maxLength": 8},
+            },
+            "additionalProperties": false,
+        }));
+
+        assert_eq!(
+            property(&conversion.schema, "code").schema_type,
+            Some(Type::String)
+        );
+        assert_eq!(
+            conversion.warnings,
+            [
+                "#: `additionalProperties` is not supported and was dropped",
+                "#/properties/code: `maxLength` is not supported and was dropped",
+                "#/properties/code: `pattern` is not supported and was dropped",
+            ]
+        );
+    }
+
+    #[test]
+    fn shape_keywords_are_an_error() {
+        let (path, message) = failure(json!({
+            "type": "object",
+            "properties": {"name": {"type": "string", "not": {"enum": [""]}}},
+        }));
+        assert_eq!(path, "#/properties/name/not");
+        assert_eq!(message, "`not` is not supported");
+
+        let (path, message) = failure(json!({"$ref": "https://example.com/schema.json"}));
+        assert_eq!(path, "#/$ref");
+        assert_eq!(
+            message,
+            r#"only local references are supported, not "https://example.com/schema.json""#
+        );
+    }
+
+    /// What `schemars` 0.8 generates for an `Order` with an optional address,
+    /// a documented enum field and a list of items.
+    #[test]
+    fn schemars_output() {
+        let conversion = convert(json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Order",
+            "description": "An order to ship.",
+            "type": "object",
+            "required": ["id", "items", "status"],
+            "properties": {
+                "id": {"type": "integer", "format": "uint32", "minimum": 0.0},
+                "note": {"default": null, "type": ["string", "null"]},
+                "items": {"type": "array", "items": {"$ref": "#/definitions/Item"}},
+                "status": {
+                    "description": "Where the order is.",
+                    "allOf": [{"$ref": "#/definitions/Status"}],
+                },
+                "shipping": {
+                    "anyOf": [{"$ref": "#/definitions/Address"}, {"type": "null"}],
+                },
+            },
+            "definitions": {
+                "Address": {
+                    "type": "object",
+                    "required": ["city"],
+                    "properties": {"city": {"type": "string"}},
+                },
+                "Item": {
+                    "type": "object",
+                    "required": ["name", "quantity"],
+                    "properties": {
+                        "name": {"type": "string"},
+                        "quantity": {"type": "integer", "format": "uint8", "minimum": 0.0},
+                    },
+                },
+                "Status": {"type": "string", "enum": ["pending", "shipped"]},
+            },
+        }));
+        let schema = &conversion.schema;
+
+        assert_eq!(schema.description.as_deref(), Some("An order to ship."));
+        assert_eq!(property(schema, "id").schema_type, Some(Type::Integer));
+        assert_eq!(property(schema, "id").format, None);
+        assert_eq!(property(schema, "note").nullable, Some(true));
+
+        let item = property(schema, "items").items.as_deref().unwrap();
+        assert_eq!(property(item, "quantity").schema_type, Some(Type::Integer));
+
+        let status = property(schema, "status");
+        assert_eq!(status.description.as_deref(), Some("Where the order is."));
+        assert_eq!(
+            status.enum_values,
+            Some(vec!["pending".to_owned(), "shipped".to_owned()])
+        );
+
+        let shipping = property(schema, "shipping");
+        assert_eq!(shipping.schema_type, Some(Type::Object));
+        assert_eq!(shipping.nullable, Some(true));
+
+        assert_eq!(
+            conversion.warnings,
+            [
+                r#"#/properties/id: format "uint32" is not supported and was dropped"#,
+                "#/properties/id: `minimum` is not supported and was dropped",
+                r#"#/definitions/Item/properties/quantity: format "uint8" is not supported and was dropped"#,
+                "#/definitions/Item/properties/quantity: `minimum` is not supported and was dropped",
+            ]
+        );
+    }
+}
@@ -1,15 +1,29 @@
 #![warn(unreachable_pub, unused_qualifications)]
 
+pub mod cache_manager;
+mod caches;
+pub mod chat;
 mod client;
+pub mod credentials;
 mod error;
-pub mod chat;
+mod files;
+mod json_schema;
+pub mod rate_limit;
+pub mod retry;
+mod schema;
+mod stream;
 pub mod types;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 pub use chat::Chat;
-pub use client::Client;
+pub use client::{Client, ClientBuilder};
 pub use error::Error;
+#[cfg(feature = "derive")]
+pub use gemini_rs_derive::{GeminiSchema, gemini_tool};
+pub use json_schema::JsonSchemaConversion;
+pub use schema::{GeminiSchema, SchemaViolation};
+pub use stream::ResponseStream;
 
 pub fn client() -> Client {
     Client::instance()
@@ -18,3 +32,9 @@ pub fn client() -> Client {
 pub fn chat(model: &str) -> Chat<chat::Text> {
     client().chat(model)
 }
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+    pub use serde_json;
+}
@@ -0,0 +1,283 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Requests-per-minute and tokens-per-minute budget for a model.
+///
+/// A limit of `None` is not enforced.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Limits {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u64>,
+}
+
+impl Limits {
+    pub fn new(requests_per_minute: u32, tokens_per_minute: u64) -> Self {
+        Self {
+            requests_per_minute: Some(requests_per_minute),
+            tokens_per_minute: Some(tokens_per_minute),
+        }
+    }
+}
+
+/// Client-side rate limiter keyed by model name.
+///
+/// Requests wait until the last minute's usage of their model leaves room for
+/// them. Token usage is estimated from the request body up front and replaced
+/// by the prompt token count the API reports, once it is known.
+///
+/// Clones share their state, so one limiter can be handed to several clients.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    default_limits: Option<Limits>,
+    limits: HashMap<Box<str>, Limits>,
+    windows: HashMap<Box<str>, VecDeque<Entry>>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    id: u64,
+    at: Instant,
+    tokens: u64,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn limit(self, model: &str, limits: Limits) -> Self {
+        self.lock().limits.insert(model_key(model).into(), limits);
+        self
+    }
+
+    /// Applied to models without limits of their own.
+    pub fn default_limits(self, limits: Limits) -> Self {
+        self.lock().default_limits = Some(limits);
+        self
+    }
+
+    /// Waits until `tokens` more tokens and one more request fit into the
+    /// model's budget, and records them.
+    ///
+    /// A single request larger than the whole token budget is let through once
+    /// the window is otherwise empty, rather than waiting forever.
+    pub(crate) async fn acquire(&self, model: &str, tokens: u64) -> Reservation {
+        let model = model_key(model);
+        loop {
+            let wait = {
+                let mut inner = self.lock();
+                let Some(limits) = inner.limits_for(model) else {
+                    return Reservation::none();
+                };
+
+                let now = Instant::now();
+                let window = inner.windows.entry(model.into()).or_default();
+                while window
+                    .front()
+                    .is_some_and(|entry| now.duration_since(entry.at) >= WINDOW)
+                {
+                    window.pop_front();
+                }
+
+                let requests_full = limits
+                    .requests_per_minute
+                    .is_some_and(|rpm| window.len() >= rpm as usize);
+                let tokens_full = limits.tokens_per_minute.is_some_and(|tpm| {
+                    !window.is_empty()
+                        && window.iter().map(|entry| entry.tokens).sum::<u64>() + tokens > tpm
+                });
+
+                match window.front() {
+                    Some(oldest) if requests_full || tokens_full => {
+                        WINDOW.saturating_sub(now.duration_since(oldest.at))
+                    }
+                    _ => {
+                        inner.next_id += 1;
+                        let id = inner.next_id;
+                        inner
+                            .windows
+                            .entry(model.into())
+                            .or_default()
+                            .push_back(Entry {
+                                id,
+                                at: now,
+                                tokens,
+                            });
+                        return Reservation {
+                            limiter: Some(self.clone()),
+                            model: model.into(),
+                            id,
+                        };
+                    }
+                }
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Inner {
+    fn limits_for(&self, model: &str) -> Option<Limits> {
+        self.limits.get(model).copied().or(self.default_limits)
+    }
+}
+
+/// A request recorded by the [`RateLimiter`], whose token estimate can be
+/// corrected later.
+pub(crate) struct Reservation {
+    limiter: Option<RateLimiter>,
+    model: Box<str>,
+    id: u64,
+}
+
+impl Reservation {
+    pub(crate) fn none() -> Self {
+        Self {
+            limiter: None,
+            model: Box::default(),
+            id: 0,
+        }
+    }
+
+    pub(crate) fn reconcile(&self, tokens: u64) {
+        let Some(limiter) = &self.limiter else {
+            return;
+        };
+
+        let mut inner = limiter.lock();
+        if let Some(entry) = inner
+            .windows
+            .get_mut(&self.model)
+            .and_then(|window| window.iter_mut().find(|entry| entry.id == self.id))
+        {
+            entry.tokens = tokens;
+        }
+    }
+}
+
+/// Rough token count for a request body, at about four bytes per token.
+pub(crate) fn estimate_tokens(body: &[u8]) -> u64 {
+    (body.len() as u64).div_ceil(4)
+}
+
+fn model_key(model: &str) -> &str {
+    model.strip_prefix("models/").unwrap_or(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Acquires `tokens` for `model` and returns how long that took.
+    async fn acquire(limiter: &RateLimiter, model: &str, tokens: u64) -> (Reservation, Duration) {
+        let start = Instant::now();
+        let reservation = limiter.acquire(model, tokens).await;
+        (reservation, start.elapsed())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn requests_wait_for_the_window() {
+        let limiter = RateLimiter::new().limit(
+            "gemini-2.0-flash",
+            Limits {
+                requests_per_minute: Some(2),
+                tokens_per_minute: None,
+            },
+        );
+
+        assert_eq!(
+            acquire(&limiter, "gemini-2.0-flash", 1).await.1,
+            Duration::ZERO
+        );
+        tokio::time::advance(Duration::from_secs(20)).await;
+        assert_eq!(
+            acquire(&limiter, "models/gemini-2.0-flash", 1).await.1,
+            Duration::ZERO
+        );
+
+        // The first request leaves the window 60 seconds after it was made.
+        let (_, waited) = acquire(&limiter, "gemini-2.0-flash", 1).await;
+        assert_eq!(waited, Duration::from_secs(40));
+        let (_, waited) = acquire(&limiter, "gemini-2.0-flash", 1).await;
+        assert_eq!(waited, Duration::from_secs(20));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tokens_wait_for_the_window() {
+        let limiter = RateLimiter::new().default_limits(Limits {
+            requests_per_minute: None,
+            tokens_per_minute: Some(100),
+        });
+
+        assert_eq!(
+            acquire(&limiter, "gemini-2.0-flash", 80).await.1,
+            Duration::ZERO
+        );
+        assert_eq!(
+            acquire(&limiter, "gemini-2.0-flash", 20).await.1,
+            Duration::ZERO
+        );
+        let (_, waited) = acquire(&limiter, "gemini-2.0-flash", 1).await;
+        assert_eq!(waited, WINDOW);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn models_are_limited_separately() {
+        let limiter = RateLimiter::new().limit("a", Limits::new(1, 1000));
+
+        assert_eq!(acquire(&limiter, "a", 1).await.1, Duration::ZERO);
+        assert_eq!(acquire(&limiter, "b", 1).await.1, Duration::ZERO);
+        assert_eq!(acquire(&limiter, "b", 1).await.1, Duration::ZERO);
+        assert_eq!(acquire(&limiter, "a", 1).await.1, WINDOW);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn oversized_request_waits_for_an_empty_window() {
+        let limiter = RateLimiter::new().default_limits(Limits::new(100, 100));
+
+        assert_eq!(acquire(&limiter, "m", 10).await.1, Duration::ZERO);
+        let (_, waited) = acquire(&limiter, "m", 500).await;
+        assert_eq!(waited, WINDOW);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconcile_corrects_the_estimate() {
+        let limiter = RateLimiter::new().default_limits(Limits::new(100, 100));
+
+        // Overestimated: the correction frees room for the next request.
+        let (reservation, _) = acquire(&limiter, "m", 90).await;
+        reservation.reconcile(10);
+        assert_eq!(acquire(&limiter, "m", 80).await.1, Duration::ZERO);
+
+        // Underestimated: the correction uses up the rest of the budget.
+        let (reservation, _) = acquire(&limiter, "m", 5).await;
+        reservation.reconcile(10);
+        let (_, waited) = acquire(&limiter, "m", 1).await;
+        assert_eq!(waited, WINDOW);
+    }
+
+    #[test]
+    fn reconcile_without_limits_does_nothing() {
+        Reservation::none().reconcile(10);
+    }
+}
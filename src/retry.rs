@@ -0,0 +1,260 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{Error, types};
+
+/// Decides whether, and after how long, a failed request is sent again.
+///
+/// When the API names a delay through `google.rpc.RetryInfo` or a
+/// `Retry-After` header, that delay is used, up to
+/// [`RetryPolicy::max_delay`]; otherwise the delay doubles with every
+/// attempt, starting at [`RetryPolicy::base_delay`], capped at
+/// [`RetryPolicy::max_delay`] and spread by [`RetryPolicy::jitter`].
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+    statuses: Vec<types::Status>,
+    http_codes: Vec<u16>,
+    transport_errors: bool,
+    on_retry: Option<RetryCallback>,
+}
+
+type RetryCallback = Arc<dyn Fn(&RetryEvent<'_>) + Send + Sync>;
+
+/// Passed to the [`RetryPolicy::on_retry`] callback before waiting.
+#[derive(Debug)]
+pub struct RetryEvent<'a> {
+    /// The attempt that just failed, starting at 1.
+    pub attempt: u32,
+    pub delay: Duration,
+    pub error: &'a Error,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(32),
+            jitter: 0.2,
+            statuses: vec![
+                types::Status::ResourceExhausted,
+                types::Status::Unavailable,
+                types::Status::Internal,
+                types::Status::DeadlineExceeded,
+            ],
+            http_codes: vec![429, 500, 502, 503, 504],
+            transport_errors: true,
+            on_retry: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .field("statuses", &self.statuses)
+            .field("http_codes", &self.http_codes)
+            .field("transport_errors", &self.transport_errors)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Longest wait between attempts, including one the server asks for.
+    /// Defaults to 32 seconds.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Fraction by which computed delays are randomly shortened or
+    /// lengthened, between `0.0` and `1.0`.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn statuses(mut self, statuses: Vec<types::Status>) -> Self {
+        self.statuses = statuses;
+        self
+    }
+
+    pub fn http_codes(mut self, codes: Vec<u16>) -> Self {
+        self.http_codes = codes;
+        self
+    }
+
+    /// Whether connection failures and timeouts are retried.
+    pub fn transport_errors(mut self, retry: bool) -> Self {
+        self.transport_errors = retry;
+        self
+    }
+
+    pub fn on_retry(mut self, callback: impl Fn(&RetryEvent<'_>) + Send + Sync + 'static) -> Self {
+        self.on_retry = Some(Arc::new(callback));
+        self
+    }
+
+    /// Returns the delay before the next attempt, or `None` if `error` should
+    /// be returned to the caller.
+    pub(crate) fn next_delay(
+        &self,
+        attempt: u32,
+        error: &Error,
+        http_code: Option<u16>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.is_retryable(error, http_code) {
+            return None;
+        }
+
+        if let Some(delay) = error.retry_after() {
+            return Some(delay.min(self.max_delay));
+        }
+
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        let spread = 1.0 + self.jitter * (2.0 * random_unit() - 1.0);
+        Some(exponential.mul_f64(spread))
+    }
+
+    pub(crate) fn notify(&self, event: &RetryEvent<'_>) {
+        if let Some(on_retry) = &self.on_retry {
+            on_retry(event);
+        }
+    }
+
+    fn is_retryable(&self, error: &Error, http_code: Option<u16>) -> bool {
+        if http_code.is_some_and(|code| self.http_codes.contains(&code)) {
+            return true;
+        }
+
+        match error {
+            Error::Gemini { detail, .. } => {
+                self.statuses.contains(&detail.status) || self.http_codes.contains(&detail.code)
+            }
+            Error::Http(error) => {
+                self.transport_errors
+                    && (error.is_connect() || error.is_timeout() || error.is_request())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A number in `[0, 1)`; good enough to spread retries, without pulling in a
+/// random number generator.
+fn random_unit() -> f64 {
+    use std::hash::BuildHasher as _;
+
+    let bits = std::collections::hash_map::RandomState::new().hash_one(std::time::Instant::now());
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn gemini(detail: serde_json::Value) -> Error {
+        Error::Gemini {
+            status: 503,
+            detail: serde_json::from_value(detail).unwrap(),
+        }
+    }
+
+    fn unavailable() -> Error {
+        gemini(json!({"code": 503, "message": "overloaded", "status": "UNAVAILABLE"}))
+    }
+
+    fn retry_info(delay: &str) -> Error {
+        gemini(json!({
+            "code": 429,
+            "message": "slow down",
+            "status": "RESOURCE_EXHAUSTED",
+            "details": [{
+                "@type": "type.googleapis.com/google.rpc.RetryInfo",
+                "retryDelay": delay,
+            }],
+        }))
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .max_attempts(10)
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(8))
+            .jitter(0.0)
+    }
+
+    #[test]
+    fn delay_doubles_up_to_the_cap() {
+        let delays = (1..=6)
+            .map(|attempt| policy().next_delay(attempt, &unavailable(), Some(503)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [1, 2, 4, 8, 8, 8].map(|secs| Some(Duration::from_secs(secs)))
+        );
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = policy().jitter(0.5);
+        for _ in 0..100 {
+            let delay = policy.next_delay(3, &unavailable(), Some(503)).unwrap();
+            assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(6));
+        }
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let policy = policy().max_attempts(3);
+        assert!(policy.next_delay(2, &unavailable(), Some(503)).is_some());
+        assert_eq!(policy.next_delay(3, &unavailable(), Some(503)), None);
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        let error = gemini(json!({"code": 400, "message": "bad", "status": "INVALID_ARGUMENT"}));
+        assert_eq!(policy().next_delay(1, &error, Some(400)), None);
+    }
+
+    #[test]
+    fn server_delay_is_used() {
+        let delay = policy().next_delay(1, &retry_info("3.5s"), Some(429));
+        assert_eq!(delay, Some(Duration::from_millis(3500)));
+    }
+
+    #[test]
+    fn server_delay_is_capped() {
+        let delay = policy().next_delay(1, &retry_info("3600s"), Some(429));
+        assert_eq!(delay, Some(Duration::from_secs(8)));
+    }
+
+    #[test]
+    fn malformed_server_delay_falls_back_to_backoff() {
+        let delay = policy().next_delay(2, &retry_info("soon"), Some(429));
+        assert_eq!(delay, Some(Duration::from_secs(2)));
+    }
+}
@@ -0,0 +1,397 @@
+use std::{
+    collections::{BTreeSet, HashSet, VecDeque},
+    fmt::Write as _,
+    rc::Rc,
+    sync::Arc,
+};
+
+use serde_json::Value;
+
+use crate::types::{Schema, Type};
+
+/// Types that can describe themselves as a response [`Schema`].
+///
+/// Implement it with `#[derive(GeminiSchema)]`, which follows the type's
+/// serde attributes so the schema matches what it deserializes from.
+/// Recursive types are not supported, as the schema has no references.
+pub trait GeminiSchema {
+    fn schema() -> Schema;
+}
+
+fn primitive(schema_type: Type, format: Option<&str>) -> Schema {
+    Schema {
+        schema_type: Some(schema_type),
+        format: format.map(Into::into),
+        ..Default::default()
+    }
+}
+
+fn array(items: Schema) -> Schema {
+    Schema {
+        schema_type: Some(Type::Array),
+        items: Some(Box::new(items)),
+        ..Default::default()
+    }
+}
+
+macro_rules! primitive {
+    ($schema_type:ident, $format:expr; $($ty:ty),*) => {
+        $(impl GeminiSchema for $ty {
+            fn schema() -> Schema {
+                primitive(Type::$schema_type, $format)
+            }
+        })*
+    };
+}
+
+primitive!(String, None; String, str, char);
+primitive!(Boolean, None; bool);
+primitive!(Integer, Some("int32"); i8, i16, i32, u8, u16);
+primitive!(Integer, Some("int64"); i64, u32, u64, isize, usize);
+primitive!(Number, Some("float"); f32);
+primitive!(Number, Some("double"); f64);
+
+impl<T: GeminiSchema> GeminiSchema for Option<T> {
+    fn schema() -> Schema {
+        Schema {
+            nullable: Some(true),
+            ..T::schema()
+        }
+    }
+}
+
+macro_rules! array {
+    ($($ty:ty),*) => {
+        $(impl<T: GeminiSchema> GeminiSchema for $ty {
+            fn schema() -> Schema {
+                array(T::schema())
+            }
+        })*
+    };
+}
+
+array!(Vec<T>, [T], VecDeque<T>, BTreeSet<T>, HashSet<T>);
+
+impl<T: GeminiSchema, const N: usize> GeminiSchema for [T; N] {
+    fn schema() -> Schema {
+        Schema {
+            min_items: Some(N.to_string()),
+            max_items: Some(N.to_string()),
+            ..array(T::schema())
+        }
+    }
+}
+
+macro_rules! transparent {
+    ($($ty:ty),*) => {
+        $(impl<T: GeminiSchema + ?Sized> GeminiSchema for $ty {
+            fn schema() -> Schema {
+                T::schema()
+            }
+        })*
+    };
+}
+
+transparent!(&T, Box<T>, Rc<T>, Arc<T>);
+
+/// A place where a JSON value does not match a [`Schema`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// Where the violation is, such as `$.people[0].name`.
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl Schema {
+    /// Checks `value` against the schema's types, required properties, enum
+    /// values, nullability and item counts, returning every violation found.
+    ///
+    /// Properties the schema does not mention are allowed, as serde ignores
+    /// them when deserializing.
+    pub fn validate(&self, value: &Value) -> Vec<SchemaViolation> {
+        let mut violations = Vec::new();
+        self.validate_at(value, &mut String::from("$"), &mut violations);
+        violations
+    }
+
+    fn validate_at(&self, value: &Value, path: &mut String, violations: &mut Vec<SchemaViolation>) {
+        let mut violation = |message: String| {
+            violations.push(SchemaViolation {
+                path: path.clone(),
+                message,
+            })
+        };
+
+        if value.is_null() {
+            if self.schema_type.is_some() && self.nullable != Some(true) {
+                violation("must not be null".into());
+            }
+            return;
+        }
+
+        if let Some(schema_type) = &self.schema_type {
+            let matches = match schema_type {
+                Type::Object => value.is_object(),
+                Type::Array => value.is_array(),
+                Type::String => value.is_string(),
+                Type::Integer => value.is_i64() || value.is_u64(),
+                Type::Number => value.is_number(),
+                Type::Boolean => value.is_boolean(),
+                // A type this version of the crate does not know cannot be
+                // checked.
+                Type::Unknown(_) => true,
+            };
+            if !matches {
+                violation(format!(
+                    "expected {}, found {}",
+                    type_name(schema_type),
+                    value_name(value)
+                ));
+                return;
+            }
+        }
+
+        if let (Some(values), Some(string)) = (&self.enum_values, value.as_str())
+            && !values.iter().any(|allowed| allowed == string)
+        {
+            violation(format!("{string:?} is not one of {values:?}"));
+        }
+
+        match value {
+            Value::Object(object) => {
+                for name in self.required.iter().flatten() {
+                    if !object.contains_key(name) {
+                        violation(format!("missing required property {name:?}"));
+                    }
+                }
+                for (name, schema) in self.properties.iter().flatten() {
+                    if let Some(value) = object.get(name) {
+                        let len = path.len();
+                        push_property(path, name);
+                        schema.validate_at(value, path, violations);
+                        path.truncate(len);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let count =
+                    |bound: &Option<String>| bound.as_deref().and_then(|b| b.parse::<usize>().ok());
+                if let Some(min) = count(&self.min_items).filter(|&min| items.len() < min) {
+                    violation(format!(
+                        "expected at least {min} items, found {}",
+                        items.len()
+                    ));
+                }
+                if let Some(max) = count(&self.max_items).filter(|&max| items.len() > max) {
+                    violation(format!(
+                        "expected at most {max} items, found {}",
+                        items.len()
+                    ));
+                }
+                if let Some(schema) = &self.items {
+                    for (index, item) in items.iter().enumerate() {
+                        let len = path.len();
+                        let _ = write!(path, "[{index}]");
+                        schema.validate_at(item, path, violations);
+                        path.truncate(len);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn push_property(path: &mut String, name: &str) {
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        path.push('.');
+        path.push_str(name);
+    } else {
+        let _ = write!(path, "[{name:?}]");
+    }
+}
+
+fn type_name(schema_type: &Type) -> &str {
+    match schema_type {
+        Type::Object => "an object",
+        Type::Array => "an array",
+        Type::String => "a string",
+        Type::Integer => "an integer",
+        Type::Number => "a number",
+        Type::Boolean => "a boolean",
+        Type::Unknown(name) => name,
+    }
+}
+
+fn value_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(number) if number.is_f64() => "a number",
+        Value::Number(_) => "an integer",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema(json: Value) -> Schema {
+        serde_json::from_value(json).unwrap()
+    }
+
+    /// Validates `value` and formats each violation as `path: message`.
+    fn violations(schema: &Schema, value: Value) -> Vec<String> {
+        schema
+            .validate(&value)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    fn people() -> Schema {
+        schema(json!({
+            "type": "object",
+            "properties": {
+                "people": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": {"type": "string"},
+                            "age": {"type": "integer"},
+                        },
+                        "required": ["name", "age"],
+                    },
+                },
+            },
+            "required": ["people"],
+        }))
+    }
+
+    #[test]
+    fn valid_value() {
+        let value = json!({"people": [{"name": "Ada", "age": 36, "extra": true}]});
+        assert!(violations(&people(), value).is_empty());
+    }
+
+    #[test]
+    fn missing_required_property() {
+        assert_eq!(
+            violations(&people(), json!({"people": [{"name": "Ada"}]})),
+            ["$.people[0]: missing required property \"age\""]
+        );
+        assert_eq!(
+            violations(&people(), json!({})),
+            ["$: missing required property \"people\""]
+        );
+    }
+
+    #[test]
+    fn paths_point_at_the_value() {
+        let value = json!({"people": [{"name": "Ada", "age": 36}, {"name": 7, "age": 1}]});
+        assert_eq!(
+            violations(&people(), value),
+            ["$.people[1].name: expected a string, found an integer"]
+        );
+
+        let schema = schema(json!({
+            "type": "object",
+            "properties": {"first name": {"type": "string"}},
+        }));
+        assert_eq!(
+            violations(&schema, json!({"first name": false})),
+            ["$[\"first name\"]: expected a string, found a boolean"]
+        );
+    }
+
+    #[test]
+    fn enum_mismatch() {
+        let schema = schema(json!({"type": "string", "enum": ["A", "B"]}));
+        assert!(violations(&schema, json!("A")).is_empty());
+        assert_eq!(
+            violations(&schema, json!("C")),
+            ["$: \"C\" is not one of [\"A\", \"B\"]"]
+        );
+    }
+
+    #[test]
+    fn null() {
+        let required = schema(json!({"type": "string"}));
+        assert_eq!(violations(&required, Value::Null), ["$: must not be null"]);
+
+        let nullable = schema(json!({"type": "string", "nullable": true}));
+        assert!(violations(&nullable, Value::Null).is_empty());
+    }
+
+    #[test]
+    fn integer_and_number() {
+        let integer = schema(json!({"type": "integer"}));
+        assert!(violations(&integer, json!(3)).is_empty());
+        assert!(violations(&integer, json!(-3)).is_empty());
+        assert_eq!(
+            violations(&integer, json!(3.5)),
+            ["$: expected an integer, found a number"]
+        );
+
+        let number = schema(json!({"type": "number"}));
+        assert!(violations(&number, json!(3)).is_empty());
+        assert!(violations(&number, json!(3.5)).is_empty());
+        assert_eq!(
+            violations(&number, json!("3")),
+            ["$: expected a number, found a string"]
+        );
+    }
+
+    #[test]
+    fn unknown_types_are_not_checked() {
+        let schema = schema(json!({"type": "tuple"}));
+        assert_eq!(schema.schema_type, Some(Type::Unknown("tuple".into())));
+        assert!(violations(&schema, json!([1, "a"])).is_empty());
+        assert!(violations(&schema, json!("a")).is_empty());
+    }
+
+    #[test]
+    fn item_counts() {
+        let schema = schema(json!({
+            "type": "array",
+            "items": {"type": "integer"},
+            "minItems": "2",
+            "maxItems": "3",
+        }));
+        assert!(violations(&schema, json!([1, 2])).is_empty());
+        assert!(violations(&schema, json!([1, 2, 3])).is_empty());
+        assert_eq!(
+            violations(&schema, json!([1])),
+            ["$: expected at least 2 items, found 1"]
+        );
+        assert_eq!(
+            violations(&schema, json!([1, 2, 3, 4])),
+            ["$: expected at most 3 items, found 4"]
+        );
+    }
+
+    #[test]
+    fn every_violation_is_reported() {
+        let value = json!({"people": [{"age": "old"}, {"name": null, "age": 2}]});
+        assert_eq!(
+            violations(&people(), value),
+            [
+                "$.people[0]: missing required property \"name\"",
+                "$.people[0].age: expected an integer, found a string",
+                "$.people[1].name: must not be null",
+            ]
+        );
+    }
+}
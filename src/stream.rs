@@ -0,0 +1,199 @@
+use futures::{StreamExt as _, stream::BoxStream};
+
+use crate::{Error, Result, types};
+
+pub type ResponseStream = BoxStream<'static, Result<types::Response>>;
+
+pub(crate) fn response_stream(response: reqwest::Response) -> ResponseStream {
+    let state = State {
+        response,
+        decoder: Decoder::default(),
+        done: false,
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        let event = state.next_event().await?;
+        Some((event, state))
+    })
+    .boxed()
+}
+
+struct State {
+    response: reqwest::Response,
+    decoder: Decoder,
+    done: bool,
+}
+
+impl State {
+    async fn next_event(&mut self) -> Option<Result<types::Response>> {
+        loop {
+            if self.done {
+                return None;
+            }
+
+            if let Some(data) = self.decoder.next_event() {
+                return Some(self.parse(&data));
+            }
+
+            match self.response.chunk().await {
+                Ok(Some(chunk)) => self.decoder.push(&chunk),
+                Ok(None) => {
+                    self.done = true;
+                    return self.decoder.finish().map(|data| self.parse(&data));
+                }
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error.into()));
+                }
+            }
+        }
+    }
+
+    fn parse(&mut self, data: &str) -> Result<types::Response> {
+        let parsed = types::ApiResponse::<types::Response>::parse(data.as_bytes());
+        match parsed {
+            Ok(types::ApiResponse::Ok(response)) => Ok(response),
+            Ok(types::ApiResponse::Err(api_error)) => {
+                self.done = true;
+                Err(Error::Gemini {
+                    status: self.response.status().as_u16(),
+                    detail: api_error.error,
+                })
+            }
+            Err(error) => {
+                self.done = true;
+                Err(error.into())
+            }
+        }
+    }
+}
+
+/// Incremental decoder for `text/event-stream` bodies.
+///
+/// Bytes are buffered until a full line is available, so events and even
+/// multi-byte characters may be split across reads.
+#[derive(Default)]
+struct Decoder {
+    buffer: Vec<u8>,
+    data: Option<String>,
+}
+
+impl Decoder {
+    fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    fn next_event(&mut self) -> Option<String> {
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let mut line = self.buffer.drain(..=end).collect::<Vec<_>>();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+
+            if line.is_empty() {
+                if let Some(data) = self.data.take() {
+                    return Some(data);
+                }
+                continue;
+            }
+
+            self.field(&String::from_utf8_lossy(&line));
+        }
+
+        None
+    }
+
+    /// Flushes whatever is left once the body has ended, tolerating a missing
+    /// trailing blank line.
+    fn finish(&mut self) -> Option<String> {
+        if !self.buffer.is_empty() {
+            self.buffer.push(b'\n');
+        }
+        self.buffer.push(b'\n');
+        self.next_event()
+    }
+
+    fn field(&mut self, line: &str) {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        if name != "data" {
+            return;
+        }
+
+        match &mut self.data {
+            Some(data) => {
+                data.push('\n');
+                data.push_str(value);
+            }
+            None => self.data = Some(value.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decoder;
+
+    /// Feeds `chunks` one read at a time and collects every event, including
+    /// the one flushed at the end of the body.
+    fn decode(chunks: &[&[u8]]) -> Vec<String> {
+        let mut decoder = Decoder::default();
+        let mut events = Vec::new();
+        for chunk in chunks {
+            decoder.push(chunk);
+            events.extend(std::iter::from_fn(|| decoder.next_event()));
+        }
+        events.extend(decoder.finish());
+        events
+    }
+
+    #[test]
+    fn events_split_mid_line() {
+        let events = decode(&[b"data: {\"a\"", b":1}\n", b"\ndata: {\"b\":2}\n\n"]);
+        assert_eq!(events, [r#"{"a":1}"#, r#"{"b":2}"#]);
+    }
+
+    #[test]
+    fn events_split_mid_character() {
+        let body = "data: \"héllo 👋\"\n\n".as_bytes();
+        let (first, rest) = body.split_at(9);
+        let (second, third) = rest.split_at(7);
+        assert!(std::str::from_utf8(first).is_err());
+
+        assert_eq!(decode(&[first, second, third]), ["\"héllo 👋\""]);
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let events = decode(&[b"data: 1\r\n\r\ndata: 2\r", b"\n\r\n"]);
+        assert_eq!(events, ["1", "2"]);
+    }
+
+    #[test]
+    fn multi_line_data() {
+        let events = decode(&[b"data: {\ndata:  \"a\": 1\ndata: }\n\n"]);
+        assert_eq!(events, ["{\n \"a\": 1\n}"]);
+    }
+
+    #[test]
+    fn comments_and_other_fields_are_ignored() {
+        let events = decode(&[b": keep-alive\n\nevent: message\nid: 7\ndata: 1\nretry: 10\n\n"]);
+        assert_eq!(events, ["1"]);
+    }
+
+    #[test]
+    fn missing_final_blank_line() {
+        assert_eq!(decode(&[b"data: 1\n\ndata: 2"]), ["1", "2"]);
+        assert_eq!(decode(&[b"data: 1\n\ndata: 2\n"]), ["1", "2"]);
+    }
+
+    #[test]
+    fn empty_body() {
+        assert!(decode(&[]).is_empty());
+        assert!(decode(&[b": only a comment\n"]).is_empty());
+    }
+}
@@ -1,19 +1,39 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, time::Duration};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+use crate::Error;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum Role {
     User,
     Model,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
+/// A response body, which is an error if it has an `error` field.
+///
+/// `Err` is tried first: responses such as [`Response`] have no required
+/// fields, so they would accept an error body too.
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 pub enum ApiResponse<T> {
-    Ok(T),
     Err(ApiError),
+    Ok(T),
+}
+
+impl<T: serde::de::DeserializeOwned> ApiResponse<T> {
+    /// Parses a body the same way, but when it is neither, fails with why it
+    /// is not a `T` rather than with "did not match any variant".
+    pub(crate) fn parse(body: &[u8]) -> serde_json::Result<Self> {
+        if let Ok(error) = serde_json::from_slice::<ApiError>(body) {
+            return Ok(Self::Err(error));
+        }
+        serde_json::from_slice(body).map(Self::Ok)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -27,32 +47,187 @@ pub struct ErrorDetail {
     pub message: String,
     pub status: Status,
     #[serde(default)]
-    pub details: Vec<ErrorInfo>,
+    pub details: Vec<StatusDetail>,
 }
 
-#[derive(Debug, Deserialize)]
+impl ErrorDetail {
+    /// The delay requested by a `google.rpc.RetryInfo` detail, if any.
+    pub fn retry_delay(&self) -> Option<Duration> {
+        self.details.iter().find_map(|detail| match detail {
+            StatusDetail::RetryInfo(info) => info.delay(),
+            _ => None,
+        })
+    }
+
+    /// The quotas a `google.rpc.QuotaFailure` detail reports as exceeded.
+    pub fn quota_violations(&self) -> impl Iterator<Item = &QuotaViolation> {
+        self.details
+            .iter()
+            .filter_map(|detail| match detail {
+                StatusDetail::QuotaFailure(failure) => Some(&failure.violations),
+                _ => None,
+            })
+            .flatten()
+    }
+}
+
+/// Parses the JSON form of `google.protobuf.Duration`, such as `"1.5s"`.
+fn parse_duration(duration: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(duration.strip_suffix('s')?.parse().ok()?).ok()
+}
+
+/// Formats a duration the way `google.protobuf.Duration` expects it in JSON.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    format!("{}s", duration.as_secs_f64())
+}
+
+/// An entry of `google.rpc.Status.details`, selected by its `@type`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "@type")]
+pub enum StatusDetail {
+    #[serde(rename = "type.googleapis.com/google.rpc.ErrorInfo")]
+    ErrorInfo(ErrorInfo),
+    #[serde(rename = "type.googleapis.com/google.rpc.RetryInfo")]
+    RetryInfo(RetryInfo),
+    #[serde(rename = "type.googleapis.com/google.rpc.QuotaFailure")]
+    QuotaFailure(QuotaFailure),
+    #[serde(rename = "type.googleapis.com/google.rpc.BadRequest")]
+    BadRequest(BadRequest),
+    #[serde(rename = "type.googleapis.com/google.rpc.Help")]
+    Help(Help),
+    #[serde(rename = "type.googleapis.com/google.rpc.LocalizedMessage")]
+    LocalizedMessage(LocalizedMessage),
+    /// Any other detail, or one whose shape did not match its `@type`.
+    #[serde(untagged)]
+    Other(serde_json::Value),
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(default)]
 pub struct ErrorInfo {
-    #[serde(rename = "@type")]
-    pub r#type: String,
-    #[serde(default)]
+    pub reason: String,
+    pub domain: String,
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RetryInfo {
+    /// A `google.protobuf.Duration`, such as `"1.5s"`.
+    pub retry_delay: Option<String>,
+}
+
+impl RetryInfo {
+    pub fn delay(&self) -> Option<Duration> {
+        parse_duration(self.retry_delay.as_deref()?)
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(default)]
+pub struct QuotaFailure {
+    pub violations: Vec<QuotaViolation>,
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct QuotaViolation {
+    pub subject: String,
+    pub description: String,
+    pub api_service: Option<String>,
+    /// Such as `generativelanguage.googleapis.com/generate_content_free_tier_requests`.
+    pub quota_metric: Option<String>,
+    /// Such as `GenerateRequestsPerMinutePerProjectPerModel-FreeTier`.
+    pub quota_id: Option<String>,
+    /// Such as the `model` and `location` the quota applies to.
+    pub quota_dimensions: BTreeMap<String, String>,
+    /// The limit that was exceeded.
+    #[serde(deserialize_with = "deserialize_int64")]
+    pub quota_value: Option<i64>,
+    /// The limit that will apply once a pending change takes effect.
+    #[serde(deserialize_with = "deserialize_int64")]
+    pub future_quota_value: Option<i64>,
+}
+
+/// Reads an `int64`, which the JSON mapping of protobuf writes as a string.
+fn deserialize_int64<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<i64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Int64 {
+        Number(i64),
+        String(String),
+    }
+
+    Ok(match Option::<Int64>::deserialize(deserializer)? {
+        Some(Int64::Number(value)) => Some(value),
+        Some(Int64::String(value)) => value.parse().ok(),
+        None => None,
+    })
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BadRequest {
+    pub field_violations: Vec<FieldViolation>,
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FieldViolation {
+    /// A path to the offending request field, such as `contents[0].parts`.
+    pub field: String,
+    pub description: String,
     pub reason: Option<String>,
-    #[serde(default)]
-    pub domain: Option<String>,
-    #[serde(default)]
-    pub metadata: Option<BTreeMap<String, String>>,
+    pub localized_message: Option<LocalizedMessage>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(default)]
+pub struct Help {
+    pub links: Vec<HelpLink>,
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(default)]
+pub struct HelpLink {
+    pub description: String,
+    pub url: String,
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(default)]
+pub struct LocalizedMessage {
+    /// A BCP-47 locale, such as `en-US`.
+    pub locale: String,
+    pub message: String,
+}
+
+/// A `google.rpc.Code`. `UNKNOWN` itself deserializes as
+/// `Status::Unknown("UNKNOWN")`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum Status {
+    Ok,
+    Cancelled,
     InvalidArgument,
-    FailedPrecondition,
-    PermissionDenied,
+    DeadlineExceeded,
     NotFound,
+    AlreadyExists,
+    PermissionDenied,
     ResourceExhausted,
+    FailedPrecondition,
+    Aborted,
+    OutOfRange,
+    Unimplemented,
     Internal,
     Unavailable,
-    DeadlineExceeded,
+    DataLoss,
+    Unauthenticated,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
 #[derive(Deserialize, Debug)]
@@ -80,35 +255,89 @@ pub struct Model {
     pub top_k: Option<i32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Response {
+    #[serde(default)]
     pub candidates: Vec<Candidate>,
     pub prompt_feedback: Option<PromptFeedback>,
     pub usage_metadata: Option<UsageMetadata>,
 }
 
+impl Response {
+    /// The function calls requested by the first candidate, in order.
+    pub fn function_calls(&self) -> impl Iterator<Item = &FunctionCall> {
+        self.candidates
+            .first()
+            .into_iter()
+            .flat_map(|candidate| &candidate.content.parts)
+            .filter_map(Part::as_function_call)
+    }
+
+    /// The text of the first candidate, with all of its text parts joined and
+    /// thoughts left out.
+    ///
+    /// Fails with [`Error::PromptBlocked`] if there is no candidate, and with
+    /// [`Error::CandidateFinished`] if the candidate has no text because it
+    /// stopped for a reason other than [`FinishReason::Stop`]. A candidate
+    /// that only calls functions yields an empty string.
+    pub fn text(&self) -> crate::Result<String> {
+        let Some(candidate) = self.candidates.first() else {
+            let feedback = self.prompt_feedback.as_ref();
+            return Err(Error::PromptBlocked {
+                reason: feedback
+                    .and_then(|feedback| feedback.block_reason.clone())
+                    .unwrap_or(BlockReason::BlockReasonUnspecified),
+                safety_ratings: feedback
+                    .map(|feedback| feedback.safety_ratings.clone())
+                    .unwrap_or_default(),
+            });
+        };
+
+        let text = self.texts().collect::<String>();
+        match &candidate.finish_reason {
+            Some(finish_reason) if text.is_empty() && *finish_reason != FinishReason::Stop => {
+                Err(Error::CandidateFinished {
+                    finish_reason: finish_reason.clone(),
+                })
+            }
+            _ => Ok(text),
+        }
+    }
+
+    fn texts(&self) -> impl Iterator<Item = &str> {
+        self.candidates
+            .first()
+            .into_iter()
+            .flat_map(|candidate| &candidate.content.parts)
+            .filter(|part| !part.is_thought())
+            .filter_map(Part::as_text)
+    }
+}
+
+/// Writes the text of the first candidate, or nothing if it has none; see
+/// [`Response::text`] to find out why.
 impl std::fmt::Display for Response {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(
-            self.candidates[0].content.parts[0]
-                .text
-                .as_deref()
-                .unwrap_or_default(),
-        )
+        self.texts().try_for_each(|text| f.write_str(text))
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct UsageMetadata {
+    #[serde(default)]
     pub prompt_token_count: u64,
+    /// Left out by the API when nothing was generated.
+    #[serde(default)]
     pub candidates_token_count: u64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Candidate {
+    /// Empty when the candidate was stopped before producing anything.
+    #[serde(default = "Content::model")]
     pub content: Content,
     pub finish_reason: Option<FinishReason>,
     pub index: Option<i32>,
@@ -116,13 +345,29 @@ pub struct Candidate {
     pub safety_ratings: Vec<SafetyRating>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct PromptFeedback {
-    #[serde(rename = "safetyRatings")]
+    pub block_reason: Option<BlockReason>,
+    #[serde(default)]
     pub safety_ratings: Vec<SafetyRating>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BlockReason {
+    BlockReasonUnspecified,
+    Safety,
+    Other,
+    Blocklist,
+    ProhibitedContent,
+    ImageSafety,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize, Clone)]
 pub struct SafetyRating {
     pub category: HarmCategory,
     pub probability: HarmProbability,
@@ -137,24 +382,214 @@ pub struct Content {
     pub parts: Vec<Part>,
 }
 
-#[derive(Debug, Default, Deserialize, Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
+impl Content {
+    fn model() -> Self {
+        Self {
+            role: Role::Model,
+            parts: Vec::new(),
+        }
+    }
+}
+
+/// One piece of a [`Content`]: its payload, plus metadata any payload can
+/// carry.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase", try_from = "WirePart")]
 pub struct Part {
+    #[serde(flatten)]
+    pub data: PartData,
+    /// Marks text as the model's reasoning rather than its answer.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub text: Option<String>,
+    pub thought: Option<bool>,
+    /// An opaque signature of the model's reasoning, which must be sent back
+    /// unchanged with the rest of the turn.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub inline_data: Option<InlineData>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub file_data: Option<FileData>,
+    pub thought_signature: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub video_metadata: Option<VideoMetadata>,
+    /// Fields next to the payload this version of the crate does not know
+    /// about yet, such as `partMetadata`, sent back unchanged.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A [`Part`] as read from the wire, before its payload is picked out of the
+/// remaining fields.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WirePart {
+    thought: Option<bool>,
+    thought_signature: Option<String>,
+    video_metadata: Option<VideoMetadata>,
+    #[serde(flatten)]
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl TryFrom<WirePart> for Part {
+    type Error = serde_json::Error;
+
+    /// Takes the first field naming a known payload as [`Part::data`] and
+    /// keeps the others in [`Part::extra`]. Without a known payload, every
+    /// field goes to [`PartData::Other`].
+    fn try_from(wire: WirePart) -> Result<Self, Self::Error> {
+        let mut fields = wire.fields;
+        let payload = PartData::KEYS
+            .iter()
+            .find_map(|key| fields.remove_entry(*key));
+        let (data, extra) = match payload {
+            Some((key, value)) => {
+                let payload = serde_json::Map::from_iter([(key, value)]);
+                (serde_json::from_value(payload.into())?, fields)
+            }
+            None => (PartData::Other(fields), serde_json::Map::new()),
+        };
+
+        Ok(Self {
+            data,
+            thought: wire.thought,
+            thought_signature: wire.thought_signature,
+            video_metadata: wire.video_metadata,
+            extra,
+        })
+    }
+}
+
+/// The payload of a [`Part`]; on the wire, the name of the one field it is
+/// sent in.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum PartData {
+    Text(String),
+    InlineData(InlineData),
+    FileData(FileData),
+    FunctionCall(FunctionCall),
+    FunctionResponse(FunctionResponse),
+    ExecutableCode(ExecutableCode),
+    CodeExecutionResult(CodeExecutionResult),
+    /// A payload this version of the crate does not know about yet, or a part
+    /// that carries only metadata, kept as its JSON fields.
+    #[serde(untagged)]
+    Other(serde_json::Map<String, serde_json::Value>),
+}
+
+impl PartData {
+    /// The field each known payload is sent in.
+    const KEYS: &[&str] = &[
+        "text",
+        "inlineData",
+        "fileData",
+        "functionCall",
+        "functionResponse",
+        "executableCode",
+        "codeExecutionResult",
+    ];
 }
 
 impl Part {
     pub fn text(text: &str) -> Self {
+        PartData::Text(text.into()).into()
+    }
+
+    pub fn function_response(response: FunctionResponse) -> Self {
+        PartData::FunctionResponse(response).into()
+    }
+
+    /// The text of a text part, thought or not.
+    pub fn as_text(&self) -> Option<&str> {
+        match &self.data {
+            PartData::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_function_call(&self) -> Option<&FunctionCall> {
+        match &self.data {
+            PartData::FunctionCall(call) => Some(call),
+            _ => None,
+        }
+    }
+
+    pub fn is_thought(&self) -> bool {
+        self.thought == Some(true)
+    }
+}
+
+impl From<PartData> for Part {
+    fn from(data: PartData) -> Self {
+        Self {
+            data,
+            thought: None,
+            thought_signature: None,
+            video_metadata: None,
+            extra: serde_json::Map::new(),
+        }
+    }
+}
+
+/// Code the model generated for the code execution tool to run.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ExecutableCode {
+    pub language: Language,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Language {
+    LanguageUnspecified,
+    Python,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// The result of running an [`ExecutableCode`].
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CodeExecutionResult {
+    pub outcome: Outcome,
+    /// Standard output on success, or the error otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Outcome {
+    OutcomeUnspecified,
+    OutcomeOk,
+    OutcomeFailed,
+    OutcomeDeadlineExceeded,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FunctionCall {
+    /// Set by the API when several calls are made at once; echo it back in the
+    /// matching [`FunctionResponse`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FunctionResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    pub response: serde_json::Value,
+}
+
+impl FunctionResponse {
+    /// A response answering `call`.
+    pub fn new(call: &FunctionCall, response: serde_json::Value) -> Self {
         Self {
-            text: Some(text.into()),
-            ..Default::default()
+            id: call.id.clone(),
+            name: call.name.clone(),
+            response,
         }
     }
 }
@@ -180,17 +615,20 @@ pub struct StartOffset {
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct FileData {
+    #[serde(alias = "mimeType")]
     pub mime_type: String,
+    #[serde(alias = "fileUri")]
     pub file_uri: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct InlineData {
+    #[serde(alias = "mimeType")]
     pub mime_type: String,
     pub data: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum FinishReason {
     FinishReasonUnspecified,
@@ -205,9 +643,18 @@ pub enum FinishReason {
     Spii,
     MalformedFunctionCall,
     ImageSafety,
+    ImageProhibitedContent,
+    ImageOther,
+    NoImage,
+    ImageRecitation,
+    UnexpectedToolCall,
+    TooManyToolCalls,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum HarmCategory {
     HarmCategoryUnspecified,
@@ -222,18 +669,28 @@ pub enum HarmCategory {
     HarmCategorySexuallyExplicit,
     HarmCategoryDangerousContent,
     HarmCategoryCivicIntegrity,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum HarmBlockThreshold {
+    HarmBlockThresholdUnspecified,
     BlockNone,
     BlockLowAndAbove,
+    #[serde(rename = "BLOCK_MEDIUM_AND_ABOVE")]
     BlockMedAndAbove,
+    #[serde(rename = "BLOCK_ONLY_HIGH")]
     BlockHighAndAbove,
+    Off,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum HarmProbability {
     HarmProbabilityUnspecified,
@@ -241,15 +698,50 @@ pub enum HarmProbability {
     Low,
     Medium,
     High,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Tools {
     #[serde(rename = "functionDeclarations")]
     pub function_declarations: Vec<FunctionDeclaration>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function_calling_config: Option<FunctionCallingConfig>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionCallingConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<FunctionCallingMode>,
+    /// Only allowed with [`FunctionCallingMode::Any`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_function_names: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FunctionCallingMode {
+    ModeUnspecified,
+    /// The model decides between calling functions and answering in text.
+    Auto,
+    /// The model always calls a function.
+    Any,
+    /// The model never calls functions.
+    None,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct FunctionDeclaration {
     pub name: String,
     pub description: String,
@@ -270,15 +762,264 @@ pub struct GenerateContent {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default, rename = "system_instruction")]
     pub system_instruction: Option<SystemInstructionContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, rename = "toolConfig")]
+    pub tool_config: Option<ToolConfig>,
+    /// Name of a [`CachedContent`] whose contents precede [`Self::contents`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, rename = "cachedContent")]
+    pub cached_content: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountTokens {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generate_content_request: Option<GenerateContentRequest>,
+}
+
+/// A [`GenerateContent`] body together with the model it is meant for, as
+/// `countTokens` expects it.
+#[derive(Debug, Serialize)]
+pub struct GenerateContentRequest {
+    pub model: String,
+    #[serde(flatten)]
+    pub body: GenerateContent,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenCount {
+    pub total_tokens: u64,
+    #[serde(default)]
+    pub cached_content_token_count: Option<u64>,
+    #[serde(default)]
+    pub prompt_tokens_details: Vec<ModalityTokenCount>,
+    #[serde(default)]
+    pub cache_tokens_details: Vec<ModalityTokenCount>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModalityTokenCount {
+    pub modality: Modality,
+    #[serde(default)]
+    pub token_count: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Modality {
+    ModalityUnspecified,
+    Text,
+    Image,
+    Video,
+    Audio,
+    Document,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbedContentRequest {
+    /// Required inside [`BatchEmbedContents`], filled in by the client.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub content: Content,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_type: Option<TaskType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dimensionality: Option<u32>,
+}
+
+impl Default for EmbedContentRequest {
+    fn default() -> Self {
+        Self {
+            model: None,
+            content: Content {
+                role: Role::User,
+                parts: Vec::new(),
+            },
+            task_type: None,
+            title: None,
+            output_dimensionality: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchEmbedContents {
+    pub requests: Vec<EmbedContentRequest>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TaskType {
+    TaskTypeUnspecified,
+    RetrievalQuery,
+    RetrievalDocument,
+    SemanticSimilarity,
+    Classification,
+    Clustering,
+    QuestionAnswering,
+    FactVerification,
+    CodeRetrievalQuery,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EmbedContentResponse {
+    pub embedding: ContentEmbedding,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct BatchEmbedContentsResponse {
+    #[serde(default)]
+    pub embeddings: Vec<ContentEmbedding>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ContentEmbedding {
+    pub values: Vec<f32>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct File {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub mime_type: String,
+    #[serde(default, skip_serializing)]
+    pub size_bytes: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub create_time: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub update_time: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub expiration_time: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub sha256_hash: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub uri: String,
+    #[serde(default, skip_serializing)]
+    pub state: FileState,
+    #[serde(default, skip_serializing)]
+    pub error: Option<FileError>,
+}
+
+impl File {
+    /// A part referencing this file, to be used in a [`Content`].
+    pub fn to_part(&self) -> Part {
+        PartData::FileData(FileData {
+            mime_type: self.mime_type.clone(),
+            file_uri: self.uri.clone(),
+        })
+        .into()
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FileState {
+    #[default]
+    StateUnspecified,
+    Processing,
+    Active,
+    Failed,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FileError {
+    #[serde(default)]
+    pub code: i32,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Files {
+    #[serde(default)]
+    pub files: Vec<File>,
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedContent {
+    #[serde(default, skip_serializing)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_instruction: Option<SystemInstructionContent>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contents: Vec<Content>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<Tools>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_config: Option<ToolConfig>,
+    /// Input only, e.g. `"300s"`; mutually exclusive with `expire_time`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expire_time: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub create_time: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub update_time: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub usage_metadata: Option<CachedContentUsageMetadata>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedContentUsageMetadata {
+    #[serde(default)]
+    pub total_token_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedContents {
+    #[serde(default)]
+    pub cached_contents: Vec<CachedContent>,
+    pub next_page_token: Option<String>,
 }
 
+/// Wraps the file returned by an upload.
 #[derive(Debug, Deserialize, Serialize)]
+pub struct FileEnvelope {
+    pub file: File,
+}
+
+/// The empty JSON object returned by deletions.
+#[derive(Debug, Deserialize)]
+pub struct Empty {}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SystemInstructionContent {
     #[serde(default)]
     pub parts: Vec<SystemInstructionPart>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SystemInstructionPart {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -325,7 +1066,7 @@ pub struct Schema {
     pub items: Option<Box<Schema>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum Type {
     Object,
@@ -334,4 +1075,238 @@ pub enum Type {
     Integer,
     Number,
     Boolean,
+    /// A value this version of the crate does not know about yet.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(json: &str) -> Response {
+        match serde_json::from_str::<ApiResponse<Response>>(json).unwrap() {
+            ApiResponse::Ok(response) => response,
+            ApiResponse::Err(error) => panic!("parsed as an error: {error:?}"),
+        }
+    }
+
+    #[test]
+    fn error_body_is_not_an_empty_response() {
+        let json = r#"{"error":{"code":429,"message":"slow down","status":"RESOURCE_EXHAUSTED"}}"#;
+        let parsed = serde_json::from_str::<ApiResponse<Response>>(json).unwrap();
+
+        let ApiResponse::Err(error) = parsed else {
+            panic!("parsed as a response");
+        };
+        assert_eq!(error.error.status, Status::ResourceExhausted);
+    }
+
+    #[test]
+    fn blocked_prompt() {
+        let response = response(
+            r#"{"promptFeedback":{"blockReason":"SAFETY"},"usageMetadata":{"promptTokenCount":8,"totalTokenCount":8}}"#,
+        );
+
+        let error = response.text().unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                Error::PromptBlocked {
+                    reason: BlockReason::Safety,
+                    ..
+                }
+            ),
+            "{error:?}"
+        );
+        assert_eq!(response.usage_metadata.unwrap().candidates_token_count, 0);
+    }
+
+    #[test]
+    fn candidate_stopped_for_safety() {
+        let response = response(
+            r#"{"candidates":[{"finishReason":"SAFETY","index":0}],"usageMetadata":{"promptTokenCount":8,"totalTokenCount":8}}"#,
+        );
+
+        let error = response.text().unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                Error::CandidateFinished {
+                    finish_reason: FinishReason::Safety
+                }
+            ),
+            "{error:?}"
+        );
+    }
+
+    /// Parses `json` as a part and serializes it back.
+    fn round_trip(json: serde_json::Value) -> (Part, serde_json::Value) {
+        let part = serde_json::from_value::<Part>(json).unwrap();
+        let serialized = serde_json::to_value(&part).unwrap();
+        (part, serialized)
+    }
+
+    #[test]
+    fn thought_text_round_trips() {
+        let json = serde_json::json!({
+            "text": "Let me think.",
+            "thought": true,
+            "thoughtSignature": "c2lnbmF0dXJl",
+        });
+        let (part, serialized) = round_trip(json.clone());
+
+        assert_eq!(part.as_text(), Some("Let me think."));
+        assert!(part.is_thought());
+        assert_eq!(part.thought_signature.as_deref(), Some("c2lnbmF0dXJl"));
+        assert_eq!(serialized, json);
+    }
+
+    #[test]
+    fn signed_function_call_round_trips() {
+        let json = serde_json::json!({
+            "functionCall": {"id": "call-1", "name": "get_weather", "args": {"city": "Paris"}},
+            "thoughtSignature": "c2lnbmF0dXJl",
+        });
+        let (part, serialized) = round_trip(json.clone());
+
+        let call = part.as_function_call().unwrap();
+        assert_eq!(call.name, "get_weather");
+        assert_eq!(call.args["city"], "Paris");
+        assert_eq!(part.thought_signature.as_deref(), Some("c2lnbmF0dXJl"));
+        assert_eq!(serialized, json);
+    }
+
+    #[test]
+    fn inline_data_round_trips() {
+        let (part, serialized) = round_trip(serde_json::json!({
+            "inlineData": {"mimeType": "image/png", "data": "iVBORw0KGgo="},
+        }));
+
+        let PartData::InlineData(data) = &part.data else {
+            panic!("{part:?}");
+        };
+        assert_eq!(data.mime_type, "image/png");
+        assert_eq!(
+            serialized,
+            serde_json::json!({
+                "inlineData": {"mime_type": "image/png", "data": "iVBORw0KGgo="},
+            })
+        );
+        assert_eq!(round_trip(serialized.clone()).1, serialized);
+    }
+
+    #[test]
+    fn file_data_with_video_metadata_round_trips() {
+        let (part, serialized) = round_trip(serde_json::json!({
+            "fileData": {
+                "mimeType": "video/mp4",
+                "fileUri": "https://generativelanguage.googleapis.com/v1beta/files/abc",
+            },
+            "videoMetadata": {
+                "startOffset": {"seconds": 10, "nanos": 0},
+                "endOffset": {"seconds": 20, "nanos": 500},
+            },
+        }));
+
+        let PartData::FileData(data) = &part.data else {
+            panic!("{part:?}");
+        };
+        assert_eq!(data.mime_type, "video/mp4");
+        assert_eq!(part.video_metadata.as_ref().unwrap().end_offset.nanos, 500);
+        assert_eq!(
+            serialized,
+            serde_json::json!({
+                "fileData": {
+                    "mime_type": "video/mp4",
+                    "file_uri": "https://generativelanguage.googleapis.com/v1beta/files/abc",
+                },
+                "videoMetadata": {
+                    "startOffset": {"seconds": 10, "nanos": 0},
+                    "endOffset": {"seconds": 20, "nanos": 500},
+                },
+            })
+        );
+        assert_eq!(round_trip(serialized.clone()).1, serialized);
+    }
+
+    #[test]
+    fn code_execution_round_trips() {
+        let code = serde_json::json!({
+            "executableCode": {"language": "PYTHON", "code": "print(1 + 1)"},
+        });
+        let (part, serialized) = round_trip(code.clone());
+        assert!(matches!(
+            &part.data,
+            PartData::ExecutableCode(ExecutableCode {
+                language: Language::Python,
+                ..
+            })
+        ));
+        assert_eq!(serialized, code);
+
+        let result = serde_json::json!({
+            "codeExecutionResult": {"outcome": "OUTCOME_OK", "output": "2\n"},
+        });
+        let (part, serialized) = round_trip(result.clone());
+        assert!(matches!(
+            &part.data,
+            PartData::CodeExecutionResult(CodeExecutionResult {
+                outcome: Outcome::OutcomeOk,
+                ..
+            })
+        ));
+        assert_eq!(serialized, result);
+    }
+
+    #[test]
+    fn unknown_fields_next_to_text_round_trip() {
+        let json = serde_json::json!({
+            "text": "hi",
+            "mediaResolution": "LOW",
+            "partMetadata": {"source": "upload"},
+        });
+        let (part, serialized) = round_trip(json.clone());
+
+        assert_eq!(part.as_text(), Some("hi"));
+        assert_eq!(part.extra["mediaResolution"], "LOW");
+        assert_eq!(serialized, json);
+    }
+
+    #[test]
+    fn unknown_fields_next_to_a_function_call_round_trip() {
+        let json = serde_json::json!({
+            "functionCall": {"name": "get_weather", "args": {"city": "Paris"}},
+            "partMetadata": {"source": "tool"},
+            "thoughtSignature": "c2lnbmF0dXJl",
+        });
+        let (part, serialized) = round_trip(json.clone());
+
+        assert_eq!(part.as_function_call().unwrap().name, "get_weather");
+        assert_eq!(part.extra["partMetadata"]["source"], "tool");
+        assert_eq!(serialized, json);
+
+        let response = response(
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Checking.","mediaResolution":"LOW"},{"functionCall":{"name":"get_weather","args":{}},"partMetadata":{}}]}}]}"#,
+        );
+        assert_eq!(response.text().unwrap(), "Checking.");
+        assert_eq!(response.function_calls().count(), 1);
+    }
+
+    #[test]
+    fn unknown_payload_round_trips() {
+        let json = serde_json::json!({
+            "toolCall": {"name": "search", "query": "rust"},
+            "thoughtSignature": "c2lnbmF0dXJl",
+        });
+        let (part, serialized) = round_trip(json.clone());
+
+        let PartData::Other(fields) = &part.data else {
+            panic!("{part:?}");
+        };
+        assert_eq!(fields["toolCall"]["query"], "rust");
+        assert!(part.extra.is_empty());
+        assert_eq!(part.thought_signature.as_deref(), Some("c2lnbmF0dXJl"));
+        assert_eq!(serialized, json);
+    }
 }
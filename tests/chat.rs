@@ -0,0 +1,41 @@
+mod common;
+
+use gemini_rs::{Error, types::BlockReason};
+
+#[tokio::test]
+async fn blocked_prompt_is_not_kept_in_history() {
+    let client = common::serve(
+        "application/json",
+        r#"{"promptFeedback":{"blockReason":"SAFETY"},"usageMetadata":{"promptTokenCount":8,"totalTokenCount":8}}"#.into(),
+    );
+    let mut chat = client.chat("gemini-2.0-flash");
+
+    let error = chat.send_message("hello").await.unwrap_err();
+    assert!(
+        matches!(
+            &error,
+            Error::PromptBlocked {
+                reason: BlockReason::Safety,
+                ..
+            }
+        ),
+        "{error:?}"
+    );
+    assert!(chat.history().is_empty());
+}
+
+#[tokio::test]
+async fn empty_candidate_is_not_kept_in_history() {
+    let client = common::serve(
+        "application/json",
+        r#"{"candidates":[{"finishReason":"SAFETY","index":0}]}"#.into(),
+    );
+    let mut chat = client.chat("gemini-2.0-flash");
+
+    let error = chat.send_message("hello").await.unwrap_err();
+    assert!(
+        matches!(&error, Error::CandidateFinished { .. }),
+        "{error:?}"
+    );
+    assert!(chat.history().is_empty());
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use gemini_rs::Error;
+
+#[tokio::test]
+async fn truncated_response_keeps_status_and_body() {
+    let client = common::serve(
+        "application/json",
+        r#"{"candidates":[{"content":{"parts":[{"text":"Hel"#.into(),
+    );
+
+    let error = client
+        .generate_content("gemini-2.0-flash")
+        .await
+        .unwrap_err();
+    let Error::Decode {
+        status,
+        body,
+        source,
+    } = &error
+    else {
+        panic!("{error:?}");
+    };
+    assert_eq!(*status, 200);
+    assert_eq!(body, r#"{"candidates":[{"content":{"parts":[{"text":"Hel"#);
+    assert!(source.is_eof(), "{source}");
+}
+
+#[tokio::test]
+async fn mismatched_response_names_the_field() {
+    let client = common::serve(
+        "application/json",
+        r#"{"candidates":[{"index":"first"}]}"#.into(),
+    );
+
+    let error = client
+        .generate_content("gemini-2.0-flash")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, Error::Decode { status: 200, .. }),
+        "{error:?}"
+    );
+    assert_eq!(
+        error.to_string(),
+        "http status 200: invalid body: invalid type: string \"first\", expected i32 at line 1 column 31"
+    );
+    let source = std::error::Error::source(&error).unwrap();
+    assert!(source.to_string().starts_with("invalid type: string"));
+}
@@ -0,0 +1,33 @@
+use std::{
+    io::{Read as _, Write as _},
+    net::TcpListener,
+};
+
+use gemini_rs::Client;
+
+/// Answers every request with a 200 carrying `body`, and returns a client
+/// pointed at it.
+pub fn serve(content_type: &str, body: String) -> Client {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let content_type = content_type.to_owned();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let mut request = [0; 8192];
+            let _ = stream.read(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: {content_type}\r\nconnection: close\r\n\r\n{body}"
+            );
+        }
+    });
+
+    Client::builder()
+        .api_key("test-key")
+        .base_url(&format!("http://{address}"))
+        .build()
+        .unwrap()
+}
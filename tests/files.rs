@@ -0,0 +1,82 @@
+mod common;
+
+use std::time::Duration;
+
+use gemini_rs::{Error, types::FileState};
+
+#[tokio::test]
+async fn waiting_for_a_file_times_out() {
+    let client = common::serve(
+        "application/json",
+        r#"{"name":"files/abc","state":"PROCESSING"}"#.into(),
+    );
+
+    let error = client
+        .wait_for_file(
+            "files/abc",
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            &error,
+            Error::FileNotReady {
+                state: FileState::Processing,
+                ..
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[tokio::test]
+async fn unspecified_file_state_counts_as_processing() {
+    let client = common::serve(
+        "application/json",
+        r#"{"name":"files/abc","state":"STATE_UNSPECIFIED"}"#.into(),
+    );
+
+    let error = client
+        .wait_for_file("files/abc", Duration::from_millis(10), Duration::ZERO)
+        .await
+        .unwrap_err();
+    let Error::FileNotReady { name, state, .. } = &error else {
+        panic!("{error:?}");
+    };
+    assert_eq!(name, "files/abc");
+    assert_eq!(*state, FileState::StateUnspecified);
+}
+
+#[tokio::test]
+async fn unknown_file_states_count_as_processing() {
+    let client = common::serve(
+        "application/json",
+        r#"{"name":"files/abc","state":"ARCHIVED"}"#.into(),
+    );
+
+    let error = client
+        .wait_for_file("files/abc", Duration::from_millis(10), Duration::ZERO)
+        .await
+        .unwrap_err();
+    let Error::FileNotReady { name, state, .. } = &error else {
+        panic!("{error:?}");
+    };
+    assert_eq!(name, "files/abc");
+    assert_eq!(*state, FileState::Unknown("ARCHIVED".into()));
+}
+
+#[tokio::test]
+async fn active_file_is_returned() {
+    let client = common::serve(
+        "application/json",
+        r#"{"name":"files/abc","state":"ACTIVE"}"#.into(),
+    );
+
+    let file = client
+        .wait_for_file("files/abc", Duration::from_secs(1), Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(file.state, FileState::Active);
+}
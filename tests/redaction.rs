@@ -0,0 +1,41 @@
+use gemini_rs::Client;
+
+const KEY: &str = "super-secret-test-key";
+
+fn client() -> Client {
+    // Nothing listens on the discard port, so every request fails to connect.
+    Client::builder()
+        .api_key(KEY)
+        .base_url("http://127.0.0.1:9")
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn http_error_does_not_leak_key() {
+    let error = client().models().await.unwrap_err();
+
+    assert!(matches!(error, gemini_rs::Error::Http(_)));
+    assert!(!error.to_string().contains(KEY));
+    assert!(!format!("{error:?}").contains(KEY));
+}
+
+#[tokio::test]
+async fn stream_error_does_not_leak_key() {
+    let mut route = client().stream_generate_content("gemini-2.0-flash");
+    route.message("hello");
+    let error = route.await.err().unwrap();
+
+    assert!(!error.to_string().contains(KEY));
+    assert!(!format!("{error:?}").contains(KEY));
+}
+
+#[test]
+fn route_and_client_do_not_leak_key() {
+    let client = client();
+    let route = client.generate_content("gemini-2.0-flash");
+
+    assert!(!route.to_string().contains(KEY));
+    assert!(!format!("{route:?}").contains(KEY));
+    assert!(!format!("{client:?}").contains(KEY));
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use futures::StreamExt as _;
+use gemini_rs::Error;
+
+const TEXT_EVENT: &str = "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"Hel\"}]}}]}\n\n";
+const ERROR_EVENT: &str =
+    "data: {\"error\":{\"code\":500,\"message\":\"boom\",\"status\":\"INTERNAL\"}}\n\n";
+
+#[tokio::test]
+async fn error_event_ends_the_stream_with_gemini_error() {
+    let client = common::serve("text/event-stream", [TEXT_EVENT, ERROR_EVENT].concat());
+    let mut route = client.stream_generate_content("gemini-2.0-flash");
+    route.message("hello");
+    let mut stream = route.await.unwrap();
+
+    assert_eq!(stream.next().await.unwrap().unwrap().text().unwrap(), "Hel");
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(
+        matches!(&error, Error::Gemini { detail, .. } if detail.message == "boom"),
+        "{error:?}"
+    );
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn blocked_message_stream_rolls_back() {
+    let client = common::serve(
+        "text/event-stream",
+        "data: {\"promptFeedback\":{\"blockReason\":\"SAFETY\"},\"usageMetadata\":{\"promptTokenCount\":8}}\n\n".into(),
+    );
+    let mut chat = client.chat("gemini-2.0-flash");
+
+    let mut stream = chat.send_message_stream("hello").await.unwrap();
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(matches!(&error, Error::PromptBlocked { .. }), "{error:?}");
+    assert!(stream.next().await.is_none());
+    drop(stream);
+
+    assert!(chat.history().is_empty());
+}